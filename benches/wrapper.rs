@@ -0,0 +1,71 @@
+//! The overhead of the wrappers, compared with the bare futures.
+//!
+//! Run with `cargo bench --features bench`. The counters are printed for each
+//! benchmark, so that changes in the number of polls or registrations show up
+//! along with the timings.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use futures::{future, prelude::*, stream};
+use tokio::runtime::current_thread::Runtime;
+use tokio_ctrlc_error::{counters, AsyncCtrlc, InterruptOptions};
+
+const ITEMS: u64 = 1000;
+
+/// A future that takes many polls to complete.
+fn busy() -> impl Future<Item = u64, Error = failure::Error> {
+    stream::iter_ok::<_, failure::Error>(0..ITEMS)
+        .and_then(|i| {
+            // Yield to the executor, so that each item takes a poll
+            let mut polled = false;
+            future::poll_fn(move || {
+                if polled {
+                    Ok(Async::Ready(i))
+                } else {
+                    polled = true;
+                    futures::task::current().notify();
+                    Ok(Async::NotReady)
+                }
+            })
+        })
+        .fold(0, |sum, i| Ok::<_, failure::Error>(sum + i))
+}
+
+fn report(name: &str) {
+    let counters = counters::counters();
+    println!(
+        "{}: {} polls, {} events, {} registrations",
+        name,
+        counters.polls(),
+        counters.events(),
+        counters.registrations()
+    );
+    counters::reset();
+}
+
+fn bench_wrapper(c: &mut Criterion) {
+    let mut rt = Runtime::new().unwrap();
+    c.bench_function("bare", |b| b.iter(|| rt.block_on(busy()).unwrap()));
+    report("bare");
+    c.bench_function("ctrlc_as_error", |b| {
+        b.iter(|| rt.block_on(busy().ctrlc_as_error()).unwrap())
+    });
+    report("ctrlc_as_error");
+    c.bench_function("poll_budget", |b| {
+        let options = InterruptOptions::new().poll_budget(64);
+        b.iter(|| {
+            rt.block_on(busy().ctrlc_as_error_with(options.clone()))
+                .unwrap()
+        })
+    });
+    report("poll_budget");
+    c.bench_function("ready", |b| {
+        b.iter(|| {
+            rt.block_on(future::ok::<_, failure::Error>(()).ctrlc_as_error())
+                .unwrap()
+        })
+    });
+    report("ready");
+}
+
+criterion_group!(benches, bench_wrapper);
+criterion_main!(benches);
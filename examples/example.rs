@@ -2,7 +2,6 @@ use failure::Fallible;
 use futures::prelude::*;
 use std::time::Duration;
 use tokio_ctrlc_error::{AsyncCtrlc, KeyboardInterrupt};
-use tokio_timer;
 
 fn lengthy_task() -> impl Future<Item = (), Error = failure::Error> {
     tokio_timer::sleep(Duration::from_secs(5)).from_err()
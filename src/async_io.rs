@@ -0,0 +1,205 @@
+//! Interrupting reads and writes without dropping the connection.
+
+use crate::listener::Listener;
+use futures::prelude::*;
+use std::io::{self, Read, Write};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+/// The state shared by `CtrlcRead` and `CtrlcWrite`.
+struct Interruptible {
+    ctrlc: Listener,
+    interrupted: bool,
+}
+
+impl Interruptible {
+    fn new(ctrlc: Listener) -> Self {
+        Interruptible {
+            ctrlc,
+            interrupted: false,
+        }
+    }
+
+    /// Fails with `io::ErrorKind::Interrupted` once ctrl+c has been pressed.
+    fn check(&mut self) -> io::Result<()> {
+        if !self.interrupted {
+            self.interrupted = self.ctrlc.poll().map_err(|e| e.into_inner())?.is_ready();
+        }
+        if self.interrupted {
+            Err(io::Error::new(
+                io::ErrorKind::Interrupted,
+                self.ctrlc.interrupt(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Reader returned by `ctrlc_read`.
+pub struct CtrlcRead<R> {
+    ctrlc: Interruptible,
+    inner: R,
+}
+
+impl<R> CtrlcRead<R> {
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for CtrlcRead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.ctrlc.check()?;
+        self.inner.read(buf)
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for CtrlcRead<R> {
+    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [u8]) -> bool {
+        self.inner.prepare_uninitialized_buffer(buf)
+    }
+}
+
+/// Writer returned by `ctrlc_write`.
+pub struct CtrlcWrite<W> {
+    ctrlc: Interruptible,
+    inner: W,
+}
+
+impl<W> CtrlcWrite<W> {
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for CtrlcWrite<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.ctrlc.check()?;
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.ctrlc.check()?;
+        self.inner.flush()
+    }
+}
+
+impl<W: AsyncWrite> AsyncWrite for CtrlcWrite<W> {
+    /// Shutting down is not interrupted, so that the connection can still be
+    /// closed cleanly after ctrl+c.
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        self.inner.shutdown()
+    }
+}
+
+pub trait AsyncCtrlcIo: Sized {
+    /// Fail the reads with an `io::Error` of kind `Interrupted`, holding a
+    /// `KeyboardInterrupt`, once ctrl+c has been pressed.
+    ///
+    /// Unlike wrapping the whole connection future with `ctrlc_as_error`, this
+    /// keeps the reader, so that e.g. a goodbye message can still be sent.
+    /// Every read after ctrl+c fails, so the helpers of `std::io` retrying on
+    /// `Interrupted`, like `Read::read_exact`, would spin: use the ones from
+    /// `tokio_io::io` instead. The reads must be done from within a task.
+    ///
+    /// # Examples
+    /// ```
+    ///     use futures::prelude::*;
+    ///     use tokio_ctrlc_error::{AsyncCtrlcIo, KeyboardInterrupt};
+    ///
+    ///     let reader = std::io::Cursor::new(b"hello".to_vec()).ctrlc_read();
+    ///     let task = tokio_io::io::read_to_end(reader, Vec::new()).map(|(_, buf)| buf);
+    ///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+    ///     match rt.block_on(task) {
+    ///         Ok(buf) => assert_eq!(buf, b"hello"),
+    ///         Err(e) => assert!(e.get_ref().unwrap().is::<KeyboardInterrupt>()),
+    ///     }
+    /// ```
+    fn ctrlc_read(self) -> CtrlcRead<Self>
+    where
+        Self: AsyncRead;
+
+    /// Like `ctrlc_read`, but for writes and flushes.
+    fn ctrlc_write(self) -> CtrlcWrite<Self>
+    where
+        Self: AsyncWrite;
+}
+
+impl<T> AsyncCtrlcIo for T {
+    fn ctrlc_read(self) -> CtrlcRead<Self>
+    where
+        Self: AsyncRead,
+    {
+        CtrlcRead {
+            ctrlc: Interruptible::new(Listener::new()),
+            inner: self,
+        }
+    }
+
+    fn ctrlc_write(self) -> CtrlcWrite<Self>
+    where
+        Self: AsyncWrite,
+    {
+        CtrlcWrite {
+            ctrlc: Interruptible::new(Listener::new()),
+            inner: self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CtrlcRead, CtrlcWrite, Interruptible};
+    use crate::{listener::Listener, InterruptKind, KeyboardInterrupt};
+    use futures::{future, prelude::*};
+    use std::io::{self, Cursor, Read, Write};
+    use tokio_io::AsyncWrite;
+
+    fn interrupted() -> Interruptible {
+        Interruptible::new(Listener::interrupted())
+    }
+
+    #[test]
+    fn test_read_write() {
+        future::lazy(|| {
+            let mut reader = CtrlcRead {
+                ctrlc: interrupted(),
+                inner: Cursor::new(b"hello".to_vec()),
+            };
+            let err = reader.read(&mut [0; 5]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+            assert!(err.get_ref().unwrap().is::<KeyboardInterrupt>());
+            assert_eq!(InterruptKind::of(&err), Some(InterruptKind::UserInterrupt));
+            // Interrupts are latched
+            assert!(reader.read(&mut [0; 5]).is_err());
+            assert_eq!(reader.get_ref().position(), 0);
+
+            let mut writer = CtrlcWrite {
+                ctrlc: interrupted(),
+                inner: Cursor::new(Vec::new()),
+            };
+            assert!(writer.write(b"hello").is_err());
+            assert!(writer.flush().is_err());
+            assert!(writer.shutdown().unwrap().is_ready());
+            Ok::<_, ()>(())
+        })
+        .wait()
+        .unwrap();
+    }
+}
@@ -0,0 +1,181 @@
+//! Interrupting collections of futures with a single ctrl+c listener.
+
+use crate::{listener::Listener, CtrlcAsError, IoError, KeyboardInterrupt};
+use futures::{future::SelectOk, prelude::*};
+use std::mem;
+
+enum Slot<F: Future> {
+    Pending(F),
+    Done(Result<F::Item, F::Error>),
+}
+
+/// Future returned by `interrupt_all`.
+pub struct InterruptAll<F: Future> {
+    ctrlc: Listener,
+    slots: Vec<Slot<F>>,
+}
+
+/// Run all the futures to completion, interrupting them on ctrl+c.
+///
+/// Resolves to the results of the futures, in the original order. On ctrl+c,
+/// the futures that have not completed yet are dropped and their results are
+/// `KeyboardInterrupt` errors. The future itself fails only if the ctrl+c
+/// handler fails.
+///
+/// All the futures share a single ctrl+c listener. To abort the whole batch on
+/// the first error instead, use `futures::future::join_all(..).ctrlc_as_error()`.
+pub fn interrupt_all<I>(futures: I) -> InterruptAll<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Future,
+{
+    interrupt_all_with(Listener::new(), futures)
+}
+
+fn interrupt_all_with<I>(ctrlc: Listener, futures: I) -> InterruptAll<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Future,
+{
+    InterruptAll {
+        ctrlc,
+        slots: futures.into_iter().map(Slot::Pending).collect(),
+    }
+}
+
+impl<F: Future> Future for InterruptAll<F>
+where
+    F::Error: From<KeyboardInterrupt> + From<IoError>,
+{
+    type Error = F::Error;
+    type Item = Vec<Result<F::Item, F::Error>>;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let interrupted = self.ctrlc.poll()?.is_ready();
+        let mut all_done = true;
+        for slot in &mut self.slots {
+            if let Slot::Pending(ref mut future) = slot {
+                let res = if interrupted {
+                    Err(self.ctrlc.interrupt().into())
+                } else {
+                    match future.poll() {
+                        Ok(Async::Ready(item)) => Ok(item),
+                        Ok(Async::NotReady) => {
+                            all_done = false;
+                            continue;
+                        }
+                        Err(e) => Err(e),
+                    }
+                };
+                *slot = Slot::Done(res);
+            }
+        }
+
+        if !all_done {
+            return Ok(Async::NotReady);
+        }
+        let results = mem::take(&mut self.slots)
+            .into_iter()
+            .map(|slot| match slot {
+                Slot::Done(res) => res,
+                Slot::Pending(_) => unreachable!(),
+            })
+            .collect();
+        Ok(Async::Ready(results))
+    }
+}
+
+/// Like `futures::future::select_ok`, but interrupted by ctrl+c.
+///
+/// Resolves to the first successful result together with the remaining
+/// futures. On ctrl+c, a `KeyboardInterrupt` error is returned immediately,
+/// without waiting for the other alternatives to fail.
+///
+/// # Panics
+/// Panics if the iterator is empty, just like `futures::future::select_ok`.
+pub fn select_ok<I>(futures: I) -> CtrlcAsError<SelectOk<I::Item>>
+where
+    I: IntoIterator,
+    I::Item: Future,
+    <I::Item as Future>::Error: From<KeyboardInterrupt> + From<IoError>,
+{
+    select_ok_with(Listener::new(), futures)
+}
+
+fn select_ok_with<I>(ctrlc: Listener, futures: I) -> CtrlcAsError<SelectOk<I::Item>>
+where
+    I: IntoIterator,
+    I::Item: Future,
+{
+    CtrlcAsError::with_listener(ctrlc, futures::future::select_ok(futures))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{interrupt_all, interrupt_all_with, select_ok, select_ok_with};
+    use crate::{listener::Listener, KeyboardInterrupt};
+    use futures::{future, prelude::*};
+    use tokio::runtime::current_thread::Runtime;
+
+    #[test]
+    fn test_interrupt_all() {
+        let futures = vec![
+            future::ok(1),
+            future::err(failure::err_msg("failed")),
+            future::ok(3),
+        ];
+        let mut rt = Runtime::new().unwrap();
+        let results = rt.block_on(interrupt_all(futures)).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(*results[0].as_ref().unwrap(), 1);
+        assert!(results[1].is_err());
+        assert_eq!(*results[2].as_ref().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_select_ok() {
+        let futures = vec![future::err(failure::err_msg("failed")), future::ok(2)];
+        let mut rt = Runtime::new().unwrap();
+        let (item, rest) = rt.block_on(select_ok(futures)).unwrap();
+        assert_eq!(item, 2);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_interrupt_all_interrupted() {
+        let futures = vec![
+            future::Either::A(future::empty::<u32, failure::Error>()),
+            future::Either::B(future::empty()),
+        ];
+        let results = interrupt_all_with(Listener::interrupted(), futures)
+            .wait()
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        let interrupts: Vec<_> = results
+            .iter()
+            .map(|res| {
+                let err = res.as_ref().unwrap_err();
+                err.downcast_ref::<KeyboardInterrupt>().unwrap().clone()
+            })
+            .collect();
+        // All the members see the same interrupt
+        assert!(interrupts[0].first_received().is_some());
+        assert_eq!(
+            interrupts[0].first_received(),
+            interrupts[1].first_received()
+        );
+        assert_eq!(interrupts[1].count(), Some(1));
+    }
+
+    #[test]
+    fn test_select_ok_interrupted() {
+        let futures = vec![
+            future::Either::A(future::err::<(), _>(failure::err_msg("failed"))),
+            future::Either::B(future::empty()),
+        ];
+        let err = select_ok_with(Listener::interrupted(), futures)
+            .wait()
+            .unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+    }
+}
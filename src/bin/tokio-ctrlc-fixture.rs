@@ -0,0 +1,73 @@
+//! A child process for end-to-end tests, started with `fixture::command`.
+//!
+//! Runs a wrapped future that never completes and interrupts itself with
+//! ctrl+c, or with `complete` as the argument, a future that completes
+//! normally. Exits with the code returned by `report`. With `signaled`, the
+//! interrupted process dies of SIGINT with `exit_as_signaled` instead of
+//! exiting, on Unix.
+//!
+//! With `teardown` as the argument, completes a wrapped future and interrupts
+//! itself afterwards, with `exit_on_unhandled_ctrlc` enabled. Unix only.
+//!
+//! With `hung-cleanup` as the argument, interrupts itself while running a
+//! wrapper whose cleanup blocks the runtime, which gets the process aborted.
+//! With `quick-cleanup`, the cleanup finishes before the cap instead, and the
+//! process outlives the cap before exiting.
+
+#[cfg(any(unix, windows))]
+fn main() {
+    use futures::{future, prelude::*};
+    use std::{env, error::Error, process, thread, time::Duration};
+    use tokio_ctrlc_error::{exit_as_signaled, fixture, report, AsyncCtrlc};
+
+    let arg = env::args().nth(1);
+    if arg.as_deref() == Some("signaled") {
+        let wrapped = future::empty::<(), Box<dyn Error + Send + Sync>>().ctrlc_as_error();
+        let raise = fixture::ctrlc_after(Duration::from_millis(100)).from_err();
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        exit_as_signaled(rt.block_on(wrapped.join(raise)));
+    }
+    let task: Box<dyn Future<Item = (), Error = failure::Error> + Send> =
+        if arg.as_deref() == Some("hung-cleanup") {
+            let cleanup = future::lazy(|| {
+                thread::sleep(Duration::from_secs(10));
+                Ok(())
+            });
+            let wrapped = future::empty::<(), _>()
+                .ctrlc_with_cleanup(cleanup, Duration::from_secs(1))
+                .abort_after(Duration::from_millis(200));
+            let raise = fixture::ctrlc_after(Duration::from_millis(100)).from_err();
+            Box::new(wrapped.join(raise).map(|_| ()))
+        } else if arg.as_deref() == Some("quick-cleanup") {
+            let wrapped = future::empty::<(), _>()
+                .ctrlc_with_cleanup(future::ok(()), Duration::from_secs(1))
+                .abort_after(Duration::from_millis(200));
+            let raise = fixture::ctrlc_after(Duration::from_millis(100)).from_err();
+            Box::new(wrapped.join(raise).map(|_| ()))
+        } else if arg.is_some() {
+            Box::new(future::ok(()).ctrlc_as_error())
+        } else {
+            let wrapped = future::empty::<(), _>().ctrlc_as_error();
+            let raise = fixture::ctrlc_after(Duration::from_millis(100)).from_err();
+            Box::new(wrapped.join(raise).map(|_| ()))
+        };
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    let code = report(rt.block_on(task));
+    if arg.as_deref() == Some("teardown") {
+        #[cfg(unix)]
+        {
+            tokio_ctrlc_error::exit_on_unhandled_ctrlc(true);
+            fixture::raise_ctrlc().unwrap();
+            thread::sleep(Duration::from_secs(1));
+        }
+    } else if arg.as_deref() == Some("quick-cleanup") {
+        thread::sleep(Duration::from_millis(400));
+    }
+    process::exit(code);
+}
+
+#[cfg(not(any(unix, windows)))]
+fn main() {
+    eprintln!("the fixture is only supported on Unix and Windows");
+    std::process::exit(1);
+}
@@ -0,0 +1,166 @@
+//! Cooperative cancellation of CPU-bound work on ctrl+c.
+
+use crate::{listener::Listener, IoError, KeyboardInterrupt};
+use futures::prelude::*;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+/// A flag that CPU-bound jobs, e.g. run on rayon or a thread pool, check to
+/// stop early.
+///
+/// `cancel_on_ctrlc` sets the flag when the user presses ctrl+c.
+///
+/// # Examples
+/// ```
+///     use futures::{prelude::*, sync::oneshot};
+///     use tokio_ctrlc_error::CancelFlag;
+///
+///     let flag = CancelFlag::new();
+///     let (tx, rx) = oneshot::channel();
+///     let job_flag = flag.clone();
+///     std::thread::spawn(move || {
+///         let mut sum = 0u64;
+///         for i in 0..1_000_000 {
+///             if job_flag.is_cancelled() {
+///                 break;
+///             }
+///             sum += i;
+///         }
+///         let _ = tx.send(sum);
+///     });
+///
+///     let task = flag.cancel_on_ctrlc(rx.map_err(failure::Error::from));
+///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+///     println!("{:?}", rt.block_on(task));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelFlag(Arc<Inner>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    /// The interrupt that set the flag, returned by `check`.
+    interrupt: Mutex<Option<KeyboardInterrupt>>,
+}
+
+impl CancelFlag {
+    /// Create a flag that isn't set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the flag, for all its clones.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Set the flag because of `interrupt`.
+    fn interrupt(&self, interrupt: KeyboardInterrupt) {
+        *self.0.interrupt.lock().unwrap() = Some(interrupt);
+        self.cancel();
+    }
+
+    /// Returns whether the flag has been set, by `cancel` or on ctrl+c.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Returns `KeyboardInterrupt` if the flag has been set, and the result of
+    /// the job otherwise. Jobs that stop early usually return a partial result
+    /// that should not be mistaken for a complete one.
+    ///
+    /// The error is the interrupt seen by `cancel_on_ctrlc`, if it set the flag.
+    pub fn check<T, E: From<KeyboardInterrupt>>(&self, value: T) -> Result<T, E> {
+        if self.is_cancelled() {
+            let interrupt = self.0.interrupt.lock().unwrap().clone();
+            Err(interrupt.unwrap_or_default().into())
+        } else {
+            Ok(value)
+        }
+    }
+
+    /// Set the flag on ctrl+c, while `future` waits for the job.
+    ///
+    /// Unlike `ctrlc_as_error`, the future is not dropped on ctrl+c, but awaited
+    /// until the job notices the flag and stops. Then its result is turned into
+    /// a `KeyboardInterrupt` error, see `check`.
+    pub fn cancel_on_ctrlc<F: Future>(&self, future: F) -> CancelOnCtrlc<F> {
+        CancelOnCtrlc {
+            ctrlc: Some(Listener::new()),
+            flag: self.clone(),
+            future,
+        }
+    }
+}
+
+/// Future returned by `CancelFlag::cancel_on_ctrlc`.
+pub struct CancelOnCtrlc<F> {
+    ctrlc: Option<Listener>,
+    flag: CancelFlag,
+    future: F,
+}
+
+impl<F: Future> Future for CancelOnCtrlc<F>
+where
+    F::Error: From<KeyboardInterrupt> + From<IoError>,
+{
+    type Error = F::Error;
+    type Item = F::Item;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Some(ref mut ctrlc) = self.ctrlc {
+            if ctrlc.poll()?.is_ready() {
+                self.flag.interrupt(ctrlc.interrupt());
+            }
+        }
+        if self.flag.is_cancelled() {
+            // There is nothing more to be notified about
+            self.ctrlc = None;
+        }
+        let item = futures::try_ready!(self.future.poll());
+        self.flag.check(item).map(Async::Ready)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CancelFlag;
+    use crate::{listener::Listener, KeyboardInterrupt};
+    use futures::{future, prelude::*};
+    use tokio::runtime::current_thread::Runtime;
+
+    #[test]
+    fn test_check() {
+        let flag = CancelFlag::new();
+        assert_eq!(flag.check::<_, failure::Error>(1).unwrap(), 1);
+        flag.cancel();
+        assert!(flag.check::<_, failure::Error>(1).is_err());
+    }
+
+    #[test]
+    fn test_cancel_on_ctrlc() {
+        let flag = CancelFlag::new();
+        let job_flag = flag.clone();
+        let job = future::poll_fn(move || {
+            if job_flag.is_cancelled() {
+                Ok(Async::Ready("partial"))
+            } else {
+                Ok(Async::NotReady)
+            }
+        });
+        let mut task = flag.cancel_on_ctrlc(job);
+        task.ctrlc = Some(Listener::interrupted());
+
+        let mut rt = Runtime::new().unwrap();
+        let res: Result<_, failure::Error> = rt.block_on(task);
+        let err = res.unwrap_err().downcast::<KeyboardInterrupt>().unwrap();
+        assert_eq!(err.count(), Some(1));
+        assert!(flag.is_cancelled());
+        assert_eq!(
+            flag.check::<_, KeyboardInterrupt>(()).unwrap_err().count(),
+            Some(1)
+        );
+    }
+}
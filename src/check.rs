@@ -0,0 +1,101 @@
+//! Checking for interrupts by hand, inside custom `Future::poll` implementations.
+
+use crate::{listener::Listener, IoError, KeyboardInterrupt};
+use futures::Async;
+
+/// A handle to check for ctrl+c at safe points of a hand-written future.
+///
+/// Checking registers the current task to be woken up on ctrl+c, so it must be
+/// done from within `poll`. Once ctrl+c has been observed, every check fails.
+///
+/// # Examples
+/// ```
+///     use futures::prelude::*;
+///     use tokio_ctrlc_error::InterruptCheck;
+///
+///     struct Copy {
+///         check: InterruptCheck,
+///         chunks_left: usize,
+///     }
+///
+///     impl Future for Copy {
+///         type Item = ();
+///         type Error = failure::Error;
+///
+///         fn poll(&mut self) -> Poll<(), failure::Error> {
+///             while self.chunks_left > 0 {
+///                 // Never stop in the middle of a chunk
+///                 self.check.pending::<failure::Error>()?;
+///                 self.chunks_left -= 1;
+///             }
+///             Ok(Async::Ready(()))
+///         }
+///     }
+///
+///     let task = Copy { check: InterruptCheck::new(), chunks_left: 3 };
+///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+///     rt.block_on(task).unwrap();
+/// ```
+pub struct InterruptCheck {
+    ctrlc: Listener,
+    interrupted: bool,
+}
+
+impl InterruptCheck {
+    pub fn new() -> Self {
+        Self::with_listener(Listener::new())
+    }
+
+    pub(crate) fn with_listener(ctrlc: Listener) -> Self {
+        InterruptCheck {
+            ctrlc,
+            interrupted: false,
+        }
+    }
+
+    /// Returns whether ctrl+c has been pressed.
+    pub fn is_interrupted(&mut self) -> Result<bool, IoError> {
+        if !self.interrupted {
+            self.interrupted = self.ctrlc.poll()? == Async::Ready(());
+        }
+        Ok(self.interrupted)
+    }
+
+    /// Fails with `KeyboardInterrupt` if ctrl+c has been pressed.
+    pub fn pending<E>(&mut self) -> Result<(), E>
+    where
+        E: From<KeyboardInterrupt> + From<IoError>,
+    {
+        if self.is_interrupted()? {
+            Err(self.ctrlc.interrupt().into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for InterruptCheck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InterruptCheck;
+    use crate::listener::Listener;
+    use futures::{future, prelude::*};
+
+    #[test]
+    fn test_check() {
+        let ctrlc = Listener::interrupted();
+        let mut check = InterruptCheck::with_listener(ctrlc);
+        let checks = future::lazy(move || {
+            let first = check.pending::<failure::Error>().is_err();
+            let second = check.pending::<failure::Error>().is_err();
+            Ok::<_, ()>((first, second))
+        });
+        // Interrupts are latched, even though the source ended
+        assert_eq!(checks.wait().unwrap(), (true, true));
+    }
+}
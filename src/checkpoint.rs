@@ -0,0 +1,146 @@
+//! Saving application state when the user presses ctrl+c.
+
+use crate::{listener::Listener, IoError, KeyboardInterrupt};
+use futures::{prelude::*, sync::mpsc::UnboundedSender};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc::Sender,
+};
+
+/// A destination for the snapshots taken by `ctrlc_checkpoint`.
+pub trait StateSink<S> {
+    /// Persist or forward the snapshot.
+    fn save(&mut self, snapshot: S) -> io::Result<()>;
+}
+
+impl<S> StateSink<S> for Sender<S> {
+    fn save(&mut self, snapshot: S) -> io::Result<()> {
+        self.send(snapshot).map_err(|_| disconnected())
+    }
+}
+
+impl<S> StateSink<S> for UnboundedSender<S> {
+    fn save(&mut self, snapshot: S) -> io::Result<()> {
+        self.unbounded_send(snapshot).map_err(|_| disconnected())
+    }
+}
+
+fn disconnected() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "snapshot receiver disconnected")
+}
+
+/// Writes serialized snapshots to a file.
+///
+/// The snapshot is first written to a temporary file next to the target,
+/// which is then renamed, so that an existing checkpoint is never left
+/// half-written.
+#[derive(Debug, Clone)]
+pub struct FileSink {
+    path: PathBuf,
+}
+
+impl FileSink {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        FileSink {
+            path: path.as_ref().to_owned(),
+        }
+    }
+}
+
+impl<S: AsRef<[u8]>> StateSink<S> for FileSink {
+    fn save(&mut self, snapshot: S) -> io::Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, snapshot)?;
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+/// Future returned by `ctrlc_checkpoint`.
+pub struct CtrlcCheckpoint<F, G, K> {
+    ctrlc: Listener,
+    future: F,
+    snapshot: G,
+    sink: K,
+}
+
+impl<F, G, K> CtrlcCheckpoint<F, G, K> {
+    pub(crate) fn new(future: F, snapshot: G, sink: K) -> Self {
+        Self::with_listener(Listener::new(), future, snapshot, sink)
+    }
+
+    fn with_listener(ctrlc: Listener, future: F, snapshot: G, sink: K) -> Self {
+        CtrlcCheckpoint {
+            ctrlc,
+            future,
+            snapshot,
+            sink,
+        }
+    }
+}
+
+impl<F, G, K, S> Future for CtrlcCheckpoint<F, G, K>
+where
+    F: Future,
+    F::Error: From<KeyboardInterrupt> + From<IoError>,
+    G: FnMut() -> S,
+    K: StateSink<S>,
+{
+    type Error = F::Error;
+    type Item = F::Item;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if self.ctrlc.poll()?.is_ready() {
+            let snapshot = (self.snapshot)();
+            self.sink.save(snapshot).map_err(IoError::from)?;
+            Err(self.ctrlc.interrupt().into())
+        } else {
+            self.future.poll()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CtrlcCheckpoint, FileSink, StateSink};
+    use crate::{listener::Listener, KeyboardInterrupt};
+    use futures::{future, prelude::*};
+    use std::{env, fs, path::PathBuf, process, sync::mpsc};
+
+    /// A path unique to the test, even across concurrent runs.
+    fn temp_path(test: &str) -> PathBuf {
+        env::temp_dir().join(format!("tokio-ctrlc-error-{}-{}", test, process::id()))
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let path = temp_path("checkpoint");
+        let ctrlc = Listener::interrupted();
+        let future = future::empty::<(), failure::Error>();
+        let task = CtrlcCheckpoint::with_listener(ctrlc, future, || "state", FileSink::new(&path));
+        let err = task.wait().unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "state");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_sink() {
+        let path = temp_path("file-sink");
+        let mut sink = FileSink::new(&path);
+        sink.save("first").unwrap();
+        sink.save("second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_channel_sink() {
+        let (mut tx, rx) = mpsc::channel();
+        tx.save(42).unwrap();
+        assert_eq!(rx.recv().unwrap(), 42);
+        drop(rx);
+        assert!(tx.save(43).is_err());
+    }
+}
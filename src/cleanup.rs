@@ -0,0 +1,439 @@
+//! Running a cleanup future on ctrl+c before failing.
+
+use crate::{
+    deadlines::Deadlines,
+    listener::Listener,
+    tasks::{self, DrainProgress, ProgressReporter},
+    InterruptOptions, IoError, KeyboardInterrupt,
+};
+use futures::prelude::*;
+use std::{
+    io, process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+enum State<F, C> {
+    Running(F, Option<C>),
+    /// The cleanup and when it started.
+    Cleaning(C, Instant),
+    Done,
+}
+
+/// The deadlines of the cleanup.
+enum Timer {
+    /// The end of the grace period.
+    Grace,
+    /// The next warning of the watchdog.
+    Watchdog,
+}
+
+/// Periodic warnings about a cleanup still running, see `watchdog`.
+#[derive(Default)]
+struct Watchdog {
+    period: Option<Duration>,
+    warn: Option<Box<dyn FnMut(Duration) + Send>>,
+    progress: Option<Box<dyn ProgressReporter + Send>>,
+    /// The tasks running when the cleanup started.
+    total: usize,
+}
+
+/// Future returned by `ctrlc_with_cleanup`.
+pub struct CtrlcWithCleanup<F, C> {
+    ctrlc: Listener,
+    state: State<F, C>,
+    grace: Duration,
+    watchdog: Watchdog,
+    deadlines: Deadlines<Timer>,
+    dump_tasks: bool,
+    abort_after: Option<Duration>,
+    abort: Option<AbortTimer>,
+}
+
+impl<F, C> CtrlcWithCleanup<F, C> {
+    pub(crate) fn new(future: F, cleanup: C, grace: Duration) -> Self {
+        Self::with_listener(Listener::new(), future, cleanup, grace)
+    }
+
+    fn with_listener(ctrlc: Listener, future: F, cleanup: C, grace: Duration) -> Self {
+        CtrlcWithCleanup {
+            ctrlc,
+            state: State::Running(future, Some(cleanup)),
+            grace,
+            watchdog: Watchdog::default(),
+            deadlines: Deadlines::new(),
+            dump_tasks: false,
+            abort_after: None,
+            abort: None,
+        }
+    }
+
+    /// Listen for ctrl+c with `options` rather than the defaults, e.g. to
+    /// only run the cleanup on a second ctrl+c with `EscalationPolicy`. The
+    /// grace period of the options, if set, replaces the one given to
+    /// `ctrlc_with_cleanup`.
+    pub fn with_options<O: Into<InterruptOptions>>(mut self, options: O) -> Self {
+        let options = options.into();
+        if let Some(grace) = options.grace {
+            self.grace = grace;
+        }
+        self.ctrlc = Listener::with_options(options);
+        self
+    }
+
+    /// Warn every `period` while the cleanup is running, e.g. "shutdown in
+    /// progress for 12s; press ctrl+c again to force".
+    ///
+    /// With the `tracing` feature, the warnings are logged. They're passed to
+    /// the callback given to `on_watchdog` as well.
+    pub fn watchdog(mut self, period: Duration) -> Self {
+        self.watchdog.period = Some(period);
+        self
+    }
+
+    /// Call `warn` with the time spent in the cleanup on every warning of the
+    /// watchdog, e.g. to print it to the terminal. Does nothing without
+    /// `watchdog`.
+    pub fn on_watchdog<W: FnMut(Duration) + Send + 'static>(mut self, warn: W) -> Self {
+        self.watchdog.warn = Some(Box::new(warn));
+        self
+    }
+
+    /// Report how many of the tasks registered with `track_task` are still
+    /// running on every warning of the watchdog, e.g. "3/7 tasks remaining",
+    /// so that a slow shutdown can be told from a hung one. Does nothing
+    /// without `watchdog`, whose period limits the rate of the reports.
+    pub fn on_progress<R: ProgressReporter + Send + 'static>(mut self, reporter: R) -> Self {
+        self.watchdog.progress = Some(Box::new(reporter));
+        self
+    }
+
+    /// When another ctrl+c forces the cleanup, print the tasks registered
+    /// with `track_task` that are still running to stderr, to tell what
+    /// blocks the shutdown.
+    pub fn dump_tasks(mut self) -> Self {
+        self.dump_tasks = true;
+        self
+    }
+
+    /// Abort the process with `std::process::abort`, leaving a core dump, if
+    /// the cleanup is still running `cap` after the interrupt.
+    ///
+    /// This is a last resort for services where a stuck shutdown is worse than
+    /// a crash. The cap is enforced by a separate thread, so it holds even if
+    /// the cleanup blocks the runtime and neither the grace period nor another
+    /// ctrl+c can stop it. It's disarmed once the future completes or is
+    /// dropped. Fails with `IoError` if the thread can't be started.
+    pub fn abort_after(mut self, cap: Duration) -> Self {
+        self.abort_after = Some(cap);
+        self
+    }
+}
+
+/// The thread aborting the process after `abort_after`, disarmed when dropped.
+struct AbortTimer {
+    disarmed: Arc<AtomicBool>,
+    thread: thread::Thread,
+}
+
+impl AbortTimer {
+    /// Start the thread calling `expired` after `cap`, unless disarmed before.
+    fn start(cap: Duration, expired: fn(Duration)) -> io::Result<Self> {
+        let disarmed = Arc::new(AtomicBool::new(false));
+        let flag = disarmed.clone();
+        let deadline = Instant::now() + cap;
+        let handle = thread::Builder::new()
+            .name("ctrlc-abort".to_owned())
+            .spawn(move || {
+                while !flag.load(Ordering::Acquire) {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return expired(cap);
+                    }
+                    // Woken up early when disarmed
+                    thread::park_timeout(deadline - now);
+                }
+            })?;
+        Ok(AbortTimer {
+            disarmed,
+            thread: handle.thread().clone(),
+        })
+    }
+}
+
+impl Drop for AbortTimer {
+    fn drop(&mut self) {
+        self.disarmed.store(true, Ordering::Release);
+        self.thread.unpark();
+    }
+}
+
+fn abort(_cap: Duration) {
+    #[cfg(feature = "tracing")]
+    tracing::error!(
+        target: "tokio_ctrlc_error::shutdown",
+        cap = ?_cap,
+        "shutdown exceeded the hard cap, aborting the process"
+    );
+    process::abort();
+}
+
+impl Watchdog {
+    fn warn(&mut self, elapsed: Duration) {
+        let progress = DrainProgress::new(tasks::running_count(), self.total, elapsed);
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            target: "tokio_ctrlc_error::shutdown",
+            elapsed = ?elapsed,
+            remaining = progress.remaining(),
+            total = progress.total(),
+            "shutdown in progress for {}s; press ctrl+c again to force",
+            elapsed.as_secs()
+        );
+        if let Some(ref mut warn) = self.warn {
+            warn(elapsed);
+        }
+        if let Some(ref mut reporter) = self.progress {
+            reporter.report(&progress);
+        }
+    }
+}
+
+impl<F, C> CtrlcWithCleanup<F, C> {
+    fn finish(&mut self) {
+        self.state = State::Done;
+        self.abort = None;
+    }
+}
+
+impl<F, C> Future for CtrlcWithCleanup<F, C>
+where
+    F: Future,
+    F::Error: From<KeyboardInterrupt> + From<IoError>,
+    C: Future<Item = (), Error = F::Error>,
+{
+    type Error = F::Error;
+    type Item = F::Item;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            match self.state {
+                State::Running(ref mut future, ref mut cleanup) => {
+                    if self.ctrlc.poll()?.is_not_ready() {
+                        return future.poll();
+                    }
+                    #[cfg(feature = "tracing")]
+                    tracing::info!(
+                        target: "tokio_ctrlc_error::shutdown",
+                        grace = ?self.grace,
+                        "running the cleanup"
+                    );
+                    let cleanup = cleanup.take().expect("cleanup taken while running");
+                    if let Some(cap) = self.abort_after {
+                        self.abort = Some(AbortTimer::start(cap, abort).map_err(IoError::from)?);
+                    }
+                    let started = Instant::now();
+                    self.watchdog.total = tasks::running_count();
+                    self.deadlines.insert(started + self.grace, Timer::Grace);
+                    if let Some(period) = self.watchdog.period {
+                        self.deadlines.insert(started + period, Timer::Watchdog);
+                    }
+                    // Drops the future
+                    self.state = State::Cleaning(cleanup, started);
+                }
+                State::Cleaning(ref mut cleanup, started) => {
+                    // Another interrupt aborts the cleanup, like the deadline
+                    if self.ctrlc.poll()?.is_ready() {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(
+                            target: "tokio_ctrlc_error::shutdown",
+                            elapsed = ?started.elapsed(),
+                            "cleanup forced by another interrupt, aborting it"
+                        );
+                        if self.dump_tasks {
+                            crate::tasks::dump(started.elapsed());
+                        }
+                        self.finish();
+                        return Err(self.ctrlc.interrupt().into());
+                    }
+                    let finished = match cleanup.poll() {
+                        Ok(finished) => finished,
+                        Err(err) => {
+                            self.finish();
+                            return Err(err);
+                        }
+                    };
+                    if finished.is_ready() {
+                        #[cfg(feature = "tracing")]
+                        tracing::info!(target: "tokio_ctrlc_error::shutdown", "cleanup finished");
+                        self.finish();
+                        return Err(self.ctrlc.interrupt().into());
+                    }
+                    loop {
+                        match self.deadlines.poll()? {
+                            Async::Ready(Timer::Grace) => {
+                                #[cfg(feature = "tracing")]
+                                tracing::warn!(
+                                    target: "tokio_ctrlc_error::shutdown",
+                                    grace = ?self.grace,
+                                    "cleanup exceeded the grace period, aborting it"
+                                );
+                                self.finish();
+                                return Err(self.ctrlc.interrupt().into());
+                            }
+                            Async::Ready(Timer::Watchdog) => {
+                                self.watchdog.warn(started.elapsed());
+                                if let Some(period) = self.watchdog.period {
+                                    let next = Instant::now() + period;
+                                    self.deadlines.insert(next, Timer::Watchdog);
+                                }
+                            }
+                            Async::NotReady => return Ok(Async::NotReady),
+                        }
+                    }
+                }
+                State::Done => panic!("poll called after completion"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AbortTimer, CtrlcWithCleanup, DrainProgress};
+    use crate::{listener::Listener, InterruptOptions, KeyboardInterrupt};
+    use futures::{future, stream};
+    use std::{
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        thread,
+        time::Duration,
+    };
+    use tokio::runtime::current_thread::Runtime;
+
+    const GRACE: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn test_cleanup() {
+        let cleaned = Arc::new(AtomicBool::new(false));
+        let flag = cleaned.clone();
+        let cleanup = future::lazy(move || {
+            flag.store(true, Ordering::SeqCst);
+            Ok(())
+        });
+        let future = future::empty::<(), failure::Error>();
+        let future =
+            CtrlcWithCleanup::with_listener(Listener::interrupted(), future, cleanup, GRACE);
+        let err = Runtime::new().unwrap().block_on(future).unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+        assert!(cleaned.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_grace_period() {
+        let future = future::empty::<(), failure::Error>();
+        let cleanup = future::empty();
+        let future =
+            CtrlcWithCleanup::with_listener(Listener::interrupted(), future, cleanup, GRACE);
+        let err = Runtime::new().unwrap().block_on(future).unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+    }
+
+    #[test]
+    fn test_cleanup_error() {
+        let future = future::empty::<(), failure::Error>();
+        let cleanup = future::err(failure::err_msg("flush failed"));
+        let future =
+            CtrlcWithCleanup::with_listener(Listener::interrupted(), future, cleanup, GRACE);
+        let err = Runtime::new().unwrap().block_on(future).unwrap_err();
+        assert_eq!(err.to_string(), "flush failed");
+    }
+
+    #[test]
+    fn test_forced() {
+        let future = future::empty::<(), failure::Error>();
+        let twice = Listener::from_stream(Box::new(stream::iter_ok(vec![(), ()])));
+        let grace = Duration::from_secs(3600);
+        let future =
+            CtrlcWithCleanup::with_listener(twice, future, future::empty(), grace).dump_tasks();
+        let err = Runtime::new().unwrap().block_on(future).unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+    }
+
+    #[test]
+    fn test_watchdog() {
+        let warnings = Arc::new(AtomicUsize::new(0));
+        let counter = warnings.clone();
+        let future = future::empty::<(), failure::Error>();
+        let grace = Duration::from_millis(100);
+        let future = CtrlcWithCleanup::with_listener(
+            Listener::interrupted(),
+            future,
+            future::empty(),
+            grace,
+        )
+        .watchdog(GRACE)
+        .on_watchdog(move |elapsed| {
+            assert!(elapsed >= GRACE);
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        Runtime::new().unwrap().block_on(future).unwrap_err();
+        assert!(warnings.load(Ordering::SeqCst) >= 2);
+    }
+
+    #[test]
+    fn test_progress() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let _task = crate::track_task("cleanup::test_progress");
+        let future = future::empty::<(), failure::Error>();
+        let grace = Duration::from_millis(100);
+        let future = CtrlcWithCleanup::with_listener(
+            Listener::interrupted(),
+            future,
+            future::empty(),
+            grace,
+        )
+        .watchdog(GRACE)
+        .on_progress(move |progress: &DrainProgress| sink.lock().unwrap().push(*progress));
+        Runtime::new().unwrap().block_on(future).unwrap_err();
+        let reports = reports.lock().unwrap();
+        assert!(!reports.is_empty());
+        assert!(reports.iter().all(|progress| progress.remaining() >= 1));
+    }
+
+    #[test]
+    fn test_with_options() {
+        let future = future::empty::<(), failure::Error>();
+        let options = InterruptOptions::new().poll_budget(4).grace(GRACE * 2);
+        let future = CtrlcWithCleanup::with_listener(
+            Listener::interrupted(),
+            future,
+            future::ok::<(), failure::Error>(()),
+            GRACE,
+        )
+        .with_options(options);
+        assert_eq!(future.ctrlc.options().poll_budget, 4);
+        assert_eq!(future.grace, GRACE * 2);
+    }
+
+    static EXPIRED: AtomicBool = AtomicBool::new(false);
+
+    #[test]
+    fn test_abort_timer() {
+        let expired = |_| EXPIRED.store(true, Ordering::SeqCst);
+        drop(AbortTimer::start(GRACE, expired).unwrap());
+        thread::sleep(GRACE * 5);
+        assert!(!EXPIRED.load(Ordering::SeqCst));
+
+        let _timer = AbortTimer::start(GRACE, expired).unwrap();
+        thread::sleep(GRACE * 5);
+        assert!(EXPIRED.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,162 @@
+//! Command line options for the interrupt handling.
+
+use crate::{EscalationPolicy, HandlerErrorPolicy, InterruptOptions};
+use std::time::Duration;
+
+/// Shutdown options for command line parsers built with clap.
+///
+/// Flatten it into the application's arguments and convert it into
+/// `InterruptOptions`:
+/// ```
+///     use clap::Parser;
+///     use tokio_ctrlc_error::{InterruptOptions, ShutdownArgs};
+///
+///     #[derive(Parser)]
+///     struct Cli {
+///         #[command(flatten)]
+///         shutdown: ShutdownArgs,
+///     }
+///
+///     let cli = Cli::parse_from(&["app", "--ignore-signal-errors", "--grace-secs", "5"]);
+///     let grace = cli.shutdown.grace();
+///     let options = InterruptOptions::from(cli.shutdown);
+/// ```
+///
+/// The grace period is used by `CtrlcWithCleanup::with_options` and the signals
+/// by `signal_as_error_with`. They're also available on their own, see `grace`
+/// and `signals`.
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct ShutdownArgs {
+    /// Keep running without ctrl+c handling if the signal handler cannot be installed
+    #[arg(long)]
+    pub ignore_signal_errors: bool,
+    /// How long to wait for the cleanup after an interrupt
+    #[arg(long, value_name = "SECS")]
+    pub grace_secs: Option<u64>,
+    /// Only exit on a second ctrl+c pressed within SECS of the first one
+    #[arg(long, value_name = "SECS")]
+    pub force_on_second: Option<u64>,
+    /// Treat these signals, e.g. TERM or SIGHUP, like ctrl+c
+    #[cfg(unix)]
+    #[arg(long = "signal", value_name = "NAME", value_parser = parse_signal)]
+    pub signals: Vec<libc::c_int>,
+}
+
+impl ShutdownArgs {
+    /// The grace period for `ctrlc_with_cleanup`, if given.
+    pub fn grace(&self) -> Option<Duration> {
+        self.grace_secs.map(Duration::from_secs)
+    }
+
+    /// The signals for `signal_as_error_with`.
+    #[cfg(unix)]
+    pub fn signals(&self) -> &[libc::c_int] {
+        &self.signals
+    }
+}
+
+#[cfg(unix)]
+fn parse_signal(name: &str) -> Result<libc::c_int, String> {
+    crate::error::signal_number(name).ok_or_else(|| format!("unknown signal: {}", name))
+}
+
+impl From<ShutdownArgs> for InterruptOptions {
+    fn from(args: ShutdownArgs) -> Self {
+        let install_error = if args.ignore_signal_errors {
+            HandlerErrorPolicy::Ignore
+        } else {
+            HandlerErrorPolicy::Propagate
+        };
+        let escalation = match args.force_on_second {
+            Some(secs) => EscalationPolicy::ForceOnSecond(Duration::from_secs(secs)),
+            None => EscalationPolicy::Immediate,
+        };
+        let options = InterruptOptions::new()
+            .on_install_error(install_error)
+            .escalation(escalation);
+        let options = match args.grace() {
+            Some(grace) => options.grace(grace),
+            None => options,
+        };
+        #[cfg(unix)]
+        let options = options.signals(&args.signals);
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShutdownArgs;
+    use crate::{EscalationPolicy, HandlerErrorPolicy, InterruptOptions};
+    use clap::Parser;
+    use std::time::Duration;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        shutdown: ShutdownArgs,
+    }
+
+    #[test]
+    fn test_args() {
+        let cli = Cli::parse_from(["app"]);
+        let options = InterruptOptions::from(cli.shutdown);
+        assert_eq!(options.install_error, HandlerErrorPolicy::Propagate);
+
+        let cli = Cli::parse_from(["app", "--ignore-signal-errors"]);
+        let options = InterruptOptions::from(cli.shutdown);
+        assert_eq!(options.install_error, HandlerErrorPolicy::Ignore);
+    }
+
+    #[test]
+    fn test_grace_secs() {
+        let cli = Cli::parse_from(["app"]);
+        assert_eq!(cli.shutdown.grace(), None);
+
+        let cli = Cli::parse_from(["app", "--grace-secs", "5"]);
+        assert_eq!(cli.shutdown.grace(), Some(Duration::from_secs(5)));
+        assert!(Cli::try_parse_from(["app", "--grace-secs", "soon"]).is_err());
+    }
+
+    #[test]
+    fn test_force_on_second() {
+        let cli = Cli::parse_from(["app"]);
+        let options = InterruptOptions::from(cli.shutdown);
+        assert_eq!(options.escalation, EscalationPolicy::Immediate);
+
+        let cli = Cli::parse_from(["app", "--force-on-second", "3"]);
+        let options = InterruptOptions::from(cli.shutdown);
+        assert_eq!(
+            options.escalation,
+            EscalationPolicy::ForceOnSecond(Duration::from_secs(3))
+        );
+    }
+
+    #[test]
+    fn test_into_options() {
+        let mut argv = vec!["app", "--ignore-signal-errors", "--grace-secs", "5"];
+        argv.extend(["--force-on-second", "3"]);
+        #[cfg(unix)]
+        argv.extend(["--signal", "TERM"]);
+        let options = InterruptOptions::from(Cli::parse_from(argv).shutdown);
+        assert_eq!(options.install_error, HandlerErrorPolicy::Ignore);
+        assert_eq!(options.grace, Some(Duration::from_secs(5)));
+        assert_eq!(
+            options.escalation,
+            EscalationPolicy::ForceOnSecond(Duration::from_secs(3))
+        );
+        #[cfg(unix)]
+        assert_eq!(options.signals, [libc::SIGTERM]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_signals() {
+        let cli = Cli::parse_from(["app"]);
+        assert!(cli.shutdown.signals().is_empty());
+
+        let cli = Cli::parse_from(["app", "--signal", "TERM", "--signal", "sighup"]);
+        assert_eq!(cli.shutdown.signals(), [libc::SIGTERM, libc::SIGHUP]);
+        assert!(Cli::try_parse_from(["app", "--signal", "SIGFOO"]).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! Interrupting futures on Windows console events other than ctrl+c.
+
+use crate::{listener::Listener, ConsoleEvent, ConsoleInterrupt, IoError};
+use futures::{prelude::*, sync::mpsc};
+use std::{
+    io,
+    sync::{Mutex, Once},
+    thread,
+    time::Duration,
+};
+use windows_sys::{
+    core::BOOL,
+    Win32::System::Console::{SetConsoleCtrlHandler, CTRL_CLOSE_EVENT},
+};
+
+/// The streams waiting for `CTRL_CLOSE_EVENT`.
+static CLOSE: Mutex<Vec<mpsc::UnboundedSender<()>>> = Mutex::new(Vec::new());
+
+unsafe extern "system" fn handler(event: u32) -> BOOL {
+    if event != CTRL_CLOSE_EVENT {
+        return 0;
+    }
+    let mut waiting = CLOSE.lock().unwrap();
+    waiting.retain(|tx| tx.unbounded_send(()).is_ok());
+    if waiting.is_empty() {
+        // Nobody handles it, so let the process be terminated
+        return 0;
+    }
+    drop(waiting);
+    // The process is terminated once the handler returns, so wait for the
+    // program to finish on its own, until the system gives up on it
+    loop {
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+/// Listen for the console window being closed.
+fn close_events() -> io::Result<mpsc::UnboundedReceiver<()>> {
+    static REGISTER: Once = Once::new();
+    let mut res = Ok(());
+    REGISTER.call_once(|| {
+        if unsafe { SetConsoleCtrlHandler(Some(handler), 1) } == 0 {
+            res = Err(io::Error::last_os_error());
+        }
+    });
+    res?;
+    let (tx, rx) = mpsc::unbounded();
+    CLOSE.lock().unwrap().push(tx);
+    Ok(rx)
+}
+
+fn listener(event: ConsoleEvent) -> Listener {
+    match event {
+        ConsoleEvent::CtrlC => Listener::new(),
+        ConsoleEvent::CtrlBreak => {
+            let events = tokio_signal::windows::Event::ctrl_break().flatten_stream();
+            Listener::for_trigger("ctrl_break", Box::new(events))
+        }
+        ConsoleEvent::Close => {
+            let events: Box<dyn Stream<Item = (), Error = io::Error> + Send> = match close_events()
+            {
+                Ok(rx) => Box::new(rx.map_err(|()| unreachable!("receivers don't fail"))),
+                Err(e) => Box::new(futures::stream::once(Err(e))),
+            };
+            Listener::for_trigger("console_close", events)
+        }
+    }
+}
+
+/// Future returned by `console_as_error`.
+pub struct ConsoleAsError<F> {
+    listeners: Vec<(ConsoleEvent, Listener)>,
+    future: F,
+}
+
+impl<F: Future> Future for ConsoleAsError<F>
+where
+    F::Error: From<ConsoleInterrupt> + From<IoError>,
+{
+    type Error = F::Error;
+    type Item = F::Item;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        for (event, listener) in &mut self.listeners {
+            if listener.poll()?.is_ready() {
+                return Err(ConsoleInterrupt::new(*event).into());
+            }
+        }
+        self.future.poll()
+    }
+}
+
+pub trait AsyncConsole<F: Future> {
+    /// Intercept any of the console `events` during execution and return a
+    /// `ConsoleInterrupt` error telling which one was received.
+    ///
+    /// This is the Windows counterpart of `signal_as_error`, treating e.g.
+    /// ctrl+break the way `ctrlc_as_error` treats ctrl+c. When the console is
+    /// closed, Windows terminates the process shortly after, so the cleanup
+    /// must be quick: the process exits as soon as `main` returns, or when the
+    /// system timeout, 5 seconds by default, elapses.
+    ///
+    /// # Examples
+    /// ```no_run
+    ///     use futures::prelude::*;
+    ///     use tokio_ctrlc_error::{AsyncConsole, ConsoleEvent};
+    ///
+    ///     let task = futures::future::ok::<_, failure::Error>(())
+    ///         .console_as_error(&[ConsoleEvent::CtrlC, ConsoleEvent::CtrlBreak, ConsoleEvent::Close]);
+    ///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+    ///     rt.block_on(task).unwrap();
+    /// ```
+    fn console_as_error(self, events: &[ConsoleEvent]) -> ConsoleAsError<F>;
+}
+
+impl<F: Future> AsyncConsole<F> for F
+where
+    F::Error: From<ConsoleInterrupt> + From<IoError>,
+{
+    fn console_as_error(self, events: &[ConsoleEvent]) -> ConsoleAsError<F> {
+        let listeners = events
+            .iter()
+            .map(|&event| (event, listener(event)))
+            .collect();
+        ConsoleAsError {
+            listeners,
+            future: self,
+        }
+    }
+}
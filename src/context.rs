@@ -0,0 +1,123 @@
+//! Compatibility with error types built with `snafu`.
+
+use crate::{listener::Listener, IoError, KeyboardInterrupt};
+use ::snafu::{ErrorCompat, IntoError};
+use futures::prelude::*;
+use std::error::Error;
+
+/// Like `CtrlcAsError`, but builds the errors using snafu context selectors.
+pub struct CtrlcContext<F, I, E> {
+    ctrlc: Listener,
+    future: F,
+    interrupted: Option<I>,
+    io: Option<E>,
+}
+
+impl<F, I, E> CtrlcContext<F, I, E> {
+    fn with_listener(ctrlc: Listener, future: F, interrupted: I, io: E) -> Self {
+        CtrlcContext {
+            ctrlc,
+            future,
+            interrupted: Some(interrupted),
+            io: Some(io),
+        }
+    }
+}
+
+impl<F, I, E> Future for CtrlcContext<F, I, E>
+where
+    F: Future,
+    F::Error: Error + ErrorCompat,
+    I: IntoError<F::Error, Source = KeyboardInterrupt>,
+    E: IntoError<F::Error, Source = IoError>,
+{
+    type Error = F::Error;
+    type Item = F::Item;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let ctrlc_fut = match self.ctrlc.poll() {
+            Ok(fut) => fut,
+            Err(e) => return Err(take(&mut self.io).into_error(e)),
+        };
+        if ctrlc_fut.is_ready() {
+            Err(take(&mut self.interrupted).into_error(self.ctrlc.interrupt()))
+        } else {
+            self.future.poll()
+        }
+    }
+}
+
+fn take<T>(selector: &mut Option<T>) -> T {
+    selector
+        .take()
+        .expect("CtrlcContext polled after returning an error")
+}
+
+pub trait AsyncCtrlcContext<F: Future> {
+    /// Intercept ctrl+c during execution and return an error in such case.
+    ///
+    /// The errors are created with the given snafu context selectors, for
+    /// variants with a `KeyboardInterrupt` and an `IoError` source respectively.
+    fn ctrlc_context<I, E>(self, interrupted: I, io: E) -> CtrlcContext<F, I, E>;
+}
+
+impl<F: Future> AsyncCtrlcContext<F> for F
+where
+    F::Error: Error + ErrorCompat,
+{
+    fn ctrlc_context<I, E>(self, interrupted: I, io: E) -> CtrlcContext<F, I, E> {
+        CtrlcContext::with_listener(Listener::new(), self, interrupted, io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AsyncCtrlcContext, CtrlcContext};
+    use crate::{listener::Listener, IoError, KeyboardInterrupt};
+    use ::snafu::Snafu;
+    use futures::{future, prelude::*, stream};
+    use std::io;
+
+    #[derive(Debug, Snafu)]
+    enum AppError {
+        #[snafu(display("interrupted"))]
+        Interrupted { source: KeyboardInterrupt },
+        #[snafu(display("cannot handle signals"))]
+        Signal { source: IoError },
+    }
+
+    #[test]
+    fn test_context() {
+        use tokio::runtime::current_thread::Runtime;
+
+        let future =
+            futures::future::ok::<_, AppError>(5).ctrlc_context(InterruptedSnafu, SignalSnafu);
+        let mut rt = Runtime::new().unwrap();
+        assert_eq!(rt.block_on(future).unwrap(), 5);
+    }
+
+    #[test]
+    fn test_context_interrupted() {
+        let ctrlc = Listener::interrupted();
+        let future = future::empty::<(), AppError>();
+        let future = CtrlcContext::with_listener(ctrlc, future, InterruptedSnafu, SignalSnafu);
+        match future.wait().unwrap_err() {
+            AppError::Interrupted { source } => assert_eq!(source.count(), Some(1)),
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn test_context_io() {
+        let err = io::Error::from(io::ErrorKind::PermissionDenied);
+        let ctrlc = Listener::from_stream(Box::new(stream::once(Err(err))));
+        let future = future::empty::<(), AppError>();
+        let future = CtrlcContext::with_listener(ctrlc, future, InterruptedSnafu, SignalSnafu);
+        match future.wait().unwrap_err() {
+            AppError::Signal { source } => {
+                assert_eq!(source.kind(), io::ErrorKind::PermissionDenied)
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Interrupts requested through a Unix control socket.
+
+use futures::{prelude::*, sync::mpsc};
+use std::{
+    fs, io,
+    os::unix::{
+        io::AsRawFd,
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+/// How often the accepting thread checks whether the stream was dropped.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Stream returned by `control_socket`, yielding an item for every accepted
+/// shutdown request.
+pub struct ControlSocket(mpsc::UnboundedReceiver<io::Result<()>>);
+
+impl Stream for ControlSocket {
+    type Error = io::Error;
+    type Item = ();
+
+    fn poll(&mut self) -> Poll<Option<()>, io::Error> {
+        match self.0.poll() {
+            Ok(Async::Ready(Some(res))) => res.map(|()| Async::Ready(Some(()))),
+            Ok(Async::Ready(None)) => Ok(Async::Ready(None)),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(()) => Ok(Async::Ready(None)),
+        }
+    }
+}
+
+/// The effective user id of the process connected to `stream`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> io::Result<libc::uid_t> {
+    let mut cred: libc::ucred = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let res = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if res != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+/// The effective user id of the process connected to `stream`.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> io::Result<libc::uid_t> {
+    let mut uid = 0;
+    let mut gid = 0;
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
+/// Removes the socket file once the accepting thread exits.
+struct Bound(UnixListener, PathBuf);
+
+impl Drop for Bound {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.1);
+    }
+}
+
+/// Listen on the Unix socket `path` for shutdown requests, e.g. sent with
+/// `request_shutdown`.
+///
+/// This lets supervisors which avoid signals, or need to know who asked for
+/// the shutdown, stop the application the same way as ctrl+c. Pass the stream
+/// to `CtrlcAsError::with_signal_source`. Every connection is a request: only
+/// the ones from processes running as the same user, or as root, are accepted,
+/// the others are closed and ignored.
+///
+/// Binding fails if `path` exists, e.g. left over by a process which crashed.
+/// The accepting is done by a thread, which removes the socket and exits
+/// shortly after the stream is dropped.
+pub fn control_socket<P: AsRef<Path>>(path: P) -> io::Result<ControlSocket> {
+    let path = path.as_ref();
+    let listener = UnixListener::bind(path)?;
+    let bound = Bound(listener, path.to_owned());
+    bound.0.set_nonblocking(true)?;
+    let uid = unsafe { libc::geteuid() };
+    let (tx, rx) = mpsc::unbounded();
+    thread::spawn(move || {
+        let bound = bound;
+        while !tx.is_closed() {
+            let res = match bound.0.accept() {
+                Ok((stream, _)) => match peer_uid(&stream) {
+                    Ok(peer) if peer == uid || peer == 0 => Ok(()),
+                    Ok(_) => continue,
+                    Err(e) => Err(e),
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(POLL_INTERVAL);
+                    continue;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => Err(e),
+            };
+            let failed = res.is_err();
+            if tx.unbounded_send(res).is_err() || failed {
+                break;
+            }
+        }
+    });
+    Ok(ControlSocket(rx))
+}
+
+/// Connect to the control socket `path`, requesting the process listening on
+/// it with `control_socket` to shut down.
+pub fn request_shutdown<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    UnixStream::connect(path).map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{control_socket, request_shutdown};
+    use crate::{CtrlcAsError, KeyboardInterrupt};
+    use futures::future;
+    use tokio::runtime::current_thread::Runtime;
+
+    #[test]
+    fn test_control_socket() {
+        let name = format!("tokio-ctrlc-error-test-{}.sock", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let source = control_socket(&path).unwrap();
+        request_shutdown(&path).unwrap();
+        let future = CtrlcAsError::with_signal_source(future::empty::<(), _>(), source);
+        let err: failure::Error = Runtime::new().unwrap().block_on(future).unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+    }
+}
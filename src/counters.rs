@@ -0,0 +1,86 @@
+//! Counters of the work done by the interrupt handling, for benchmarks.
+//!
+//! Each wrapper polls its ctrl+c listener before the wrapped future, so the
+//! counters tell how much a wrapper costs when embedded in a hot loop. They
+//! are process-wide and only maintained with the `bench` feature.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static POLLS: AtomicUsize = AtomicUsize::new(0);
+static EVENTS: AtomicUsize = AtomicUsize::new(0);
+static REGISTRATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// A snapshot of the counters, see `counters`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Counters {
+    polls: usize,
+    events: usize,
+    registrations: usize,
+}
+
+impl Counters {
+    /// The number of times a listener was polled.
+    pub fn polls(&self) -> usize {
+        self.polls
+    }
+
+    /// The number of events delivered to the listeners, i.e. the interrupts
+    /// and the ends of the signal streams.
+    pub fn events(&self) -> usize {
+        self.events
+    }
+
+    /// The number of signal registrations. Each one allocates the boxed future
+    /// registering the handler and the boxed stream of the signal.
+    pub fn registrations(&self) -> usize {
+        self.registrations
+    }
+}
+
+/// Returns the current values of the counters.
+pub fn counters() -> Counters {
+    Counters {
+        polls: POLLS.load(Ordering::Relaxed),
+        events: EVENTS.load(Ordering::Relaxed),
+        registrations: REGISTRATIONS.load(Ordering::Relaxed),
+    }
+}
+
+/// Sets all the counters to zero.
+pub fn reset() {
+    POLLS.store(0, Ordering::Relaxed);
+    EVENTS.store(0, Ordering::Relaxed);
+    REGISTRATIONS.store(0, Ordering::Relaxed);
+}
+
+pub(crate) fn poll() {
+    POLLS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn event() {
+    EVENTS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn registration() {
+    REGISTRATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::counters;
+    use crate::AsyncCtrlc;
+    use futures::{future, prelude::*};
+
+    #[test]
+    fn test_counters() {
+        // Other tests run in parallel, so only check the lower bounds
+        let before = counters();
+        future::ok::<_, failure::Error>(())
+            .ctrlc_as_error()
+            .wait()
+            .unwrap();
+        let after = counters();
+        assert!(after.polls() > before.polls());
+        assert!(after.registrations() > before.registrations());
+    }
+}
@@ -0,0 +1,71 @@
+//! The deadlines of a wrapper, driven by a single timer.
+
+use crate::IoError;
+use futures::prelude::*;
+use std::time::Instant;
+use tokio_timer::Delay;
+
+/// The pending deadlines of a wrapper, each returning its key once it
+/// elapses. Only the earliest one is registered with the timer.
+pub(crate) struct Deadlines<K> {
+    pending: Vec<(Instant, K)>,
+    timer: Option<Delay>,
+}
+
+impl<K> Deadlines<K> {
+    pub(crate) fn new() -> Self {
+        Deadlines {
+            pending: Vec::new(),
+            timer: None,
+        }
+    }
+
+    /// Add a deadline at `at`, returning `key` once it elapses.
+    pub(crate) fn insert(&mut self, at: Instant, key: K) {
+        self.pending.push((at, key));
+    }
+
+    /// Returns the key of the earliest deadline if it has elapsed, removing
+    /// it. Otherwise, the task is notified once it elapses.
+    pub(crate) fn poll(&mut self) -> Poll<K, IoError> {
+        let (index, at) = match self
+            .pending
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, &(at, _))| at)
+        {
+            Some((index, &(at, _))) => (index, at),
+            None => return Ok(Async::NotReady),
+        };
+        let timer = self.timer.get_or_insert_with(|| Delay::new(at));
+        if timer.deadline() != at {
+            timer.reset(at);
+        }
+        if timer.poll().map_err(IoError::timer)?.is_not_ready() {
+            return Ok(Async::NotReady);
+        }
+        Ok(Async::Ready(self.pending.swap_remove(index).1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Deadlines;
+    use futures::future;
+    use std::time::{Duration, Instant};
+    use tokio::runtime::current_thread::Runtime;
+
+    #[test]
+    fn test_deadlines() {
+        let mut rt = Runtime::new().unwrap();
+        let mut deadlines = Deadlines::new();
+        let now = Instant::now();
+        deadlines.insert(now + Duration::from_millis(20), 2);
+        deadlines.insert(now + Duration::from_millis(10), 1);
+        let mut next = || rt.block_on(future::poll_fn(|| deadlines.poll())).unwrap();
+        assert_eq!(next(), 1);
+        assert!(now.elapsed() >= Duration::from_millis(10));
+        assert_eq!(next(), 2);
+        assert!(now.elapsed() >= Duration::from_millis(20));
+    }
+}
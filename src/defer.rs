@@ -0,0 +1,40 @@
+//! Time-bounded windows in which interrupts are deferred.
+
+use std::{
+    convert::TryFrom,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+/// The end of the window, in nanoseconds since `base`, or 0 if there is none.
+static UNTIL: AtomicU64 = AtomicU64::new(0);
+
+fn base() -> Instant {
+    static BASE: OnceLock<Instant> = OnceLock::new();
+    *BASE.get_or_init(Instant::now)
+}
+
+/// Defer interrupts for the next `duration`, e.g. while writing the trailer
+/// of an archive.
+///
+/// A ctrl+c received during the window is latched and delivered to the
+/// wrappers once it ends. The window applies to the whole process. Calling
+/// this again never shortens the current window, only extends it.
+pub fn uninterruptible_for(duration: Duration) {
+    let until = base().elapsed() + duration;
+    let until = u64::try_from(until.as_nanos()).unwrap_or(u64::MAX);
+    UNTIL.fetch_max(until, Ordering::AcqRel);
+}
+
+/// The end of the current window, if any.
+pub(crate) fn deferred_until() -> Option<Instant> {
+    let until = Duration::from_nanos(UNTIL.load(Ordering::Acquire));
+    if until > base().elapsed() {
+        Some(base() + until)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,107 @@
+//! Background tasks that don't outlive the first interrupt.
+
+use crate::listener::Listener;
+use futures::{prelude::*, sync::oneshot};
+
+struct DetachedTask<F> {
+    ctrlc: Listener,
+    future: F,
+    done: Option<oneshot::Sender<bool>>,
+}
+
+impl<F: Future<Item = (), Error = ()>> Future for DetachedTask<F> {
+    type Error = ();
+    type Item = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        // A failing handler doesn't stop the task, it only won't be dropped
+        let completed = if let Ok(Async::Ready(())) = self.ctrlc.poll() {
+            false
+        } else {
+            match self.future.poll() {
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                _ => true,
+            }
+        };
+        if let Some(done) = self.done.take() {
+            let _ = done.send(completed);
+        }
+        Ok(Async::Ready(()))
+    }
+}
+
+/// Handle to a task spawned by `detach_until_shutdown`.
+///
+/// Resolves to `true` once the task completes, or to `false` if it was dropped
+/// because of an interrupt, so that a coordinator can wait for the background
+/// work before exiting. Dropping the handle doesn't affect the task. Never
+/// fails.
+pub struct Detached(oneshot::Receiver<bool>);
+
+impl Future for Detached {
+    type Error = ();
+    type Item = bool;
+
+    fn poll(&mut self) -> Poll<bool, ()> {
+        match self.0.poll() {
+            Ok(res) => Ok(res),
+            // The executor dropped the task, e.g. when shutting down
+            Err(oneshot::Canceled) => Ok(Async::Ready(false)),
+        }
+    }
+}
+
+/// Spawn `future` on the default executor, dropping it on the first ctrl+c.
+///
+/// This covers background work that may be forgotten about, just not past
+/// shutdown, e.g. refreshing a cache. To let the work stop cooperatively
+/// instead, check a `CancelFlag` in it.
+///
+/// # Panics
+/// Panics outside of a tokio runtime, which `future` is spawned on.
+pub fn detach_until_shutdown<F>(future: F) -> Detached
+where
+    F: Future<Item = (), Error = ()> + Send + 'static,
+{
+    detach(Listener::new(), future)
+}
+
+fn detach<F>(ctrlc: Listener, future: F) -> Detached
+where
+    F: Future<Item = (), Error = ()> + Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    tokio_executor::spawn(DetachedTask {
+        ctrlc,
+        future,
+        done: Some(tx),
+    });
+    Detached(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detach, detach_until_shutdown};
+    use crate::listener::Listener;
+    use futures::future;
+    use tokio::runtime::Runtime;
+
+    #[test]
+    fn test_completed() {
+        let mut rt = Runtime::new().unwrap();
+        let handle = rt.block_on(future::lazy(|| {
+            Ok::<_, ()>(detach_until_shutdown(future::ok(())))
+        }));
+        assert!(rt.block_on(handle.unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_interrupted() {
+        let mut rt = Runtime::new().unwrap();
+        let handle = rt.block_on(future::lazy(|| {
+            let ctrlc = Listener::interrupted();
+            Ok::<_, ()>(detach(ctrlc, future::empty()))
+        }));
+        assert!(!rt.block_on(handle.unwrap()).unwrap());
+    }
+}
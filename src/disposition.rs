@@ -0,0 +1,284 @@
+//! Inspecting and restoring the dispositions of the signals this crate
+//! listens for.
+
+use crate::{error::SIGINT_EXIT_CODE, listener};
+use std::{
+    error::Error,
+    fmt, io, mem, ptr,
+    sync::{
+        atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering},
+        Mutex, Once,
+    },
+};
+
+#[derive(Clone, Copy)]
+struct Saved {
+    signum: libc::c_int,
+    /// The disposition before this crate installed its handler.
+    previous: libc::sigaction,
+    /// The disposition right after this crate installed its handler.
+    ours: Option<libc::sigaction>,
+    /// Whether `uninstall` restored the previous disposition.
+    uninstalled: bool,
+}
+
+/// The dispositions of every signal this crate has installed a handler for.
+static SAVED: Mutex<Vec<Saved>> = Mutex::new(Vec::new());
+/// Whether the SIGINT handler of this crate has been recorded in `SAVED` and
+/// is in place, so that listeners installed later don't have to take the lock.
+static RECORDED: AtomicBool = AtomicBool::new(false);
+
+/// The pid of the sender of the last SIGINT, 0 if it came from the kernel.
+static SENDER: AtomicI32 = AtomicI32::new(0);
+
+/// The number of SIGINTs received while no wrapper was alive.
+static UNHANDLED: AtomicUsize = AtomicUsize::new(0);
+/// Whether to exit on a SIGINT received while no wrapper is alive.
+static EXIT_ON_UNHANDLED: AtomicBool = AtomicBool::new(false);
+
+/// A problem with the SIGINT handler, found by `diagnose_ctrlc_handler`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HandlerConflict {
+    /// Another component installed a SIGINT handler. Interrupts will still be
+    /// intercepted once this crate installs its own: the previous handler is
+    /// run as well.
+    Foreign,
+    /// SIGINT is ignored, e.g. because the process was started in the
+    /// background. It will be intercepted once this crate installs its handler.
+    Ignored,
+    /// The handler of this crate was replaced by someone else. Interrupts are
+    /// no longer intercepted.
+    Replaced,
+}
+
+impl fmt::Display for HandlerConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            HandlerConflict::Foreign => "a foreign SIGINT handler is installed",
+            HandlerConflict::Ignored => "SIGINT is ignored",
+            HandlerConflict::Replaced => "the ctrl+c handler was replaced by a foreign one",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl Error for HandlerConflict {}
+
+fn current(signum: libc::c_int) -> io::Result<libc::sigaction> {
+    unsafe {
+        let mut old: libc::sigaction = mem::zeroed();
+        if libc::sigaction(signum, ptr::null(), &mut old) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(old)
+    }
+}
+
+fn set(signum: libc::c_int, action: &libc::sigaction) -> io::Result<()> {
+    if unsafe { libc::sigaction(signum, action, ptr::null_mut()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Check whether the SIGINT disposition is what this crate expects.
+///
+/// Before any wrapper has installed the handler, reports handlers installed
+/// by other components. Afterwards, reports whether the handler of this crate
+/// has been replaced, which is the usual reason for ctrl+c handling to stop
+/// working after adding a dependency.
+pub fn diagnose_ctrlc_handler() -> io::Result<Result<(), HandlerConflict>> {
+    let current = current(libc::SIGINT)?.sa_sigaction;
+    let saved = SAVED.lock().unwrap();
+    let ours = saved
+        .iter()
+        .find(|saved| saved.signum == libc::SIGINT && !saved.uninstalled)
+        .and_then(|saved| saved.ours);
+    let res = match ours {
+        Some(ours) => {
+            if current == ours.sa_sigaction {
+                Ok(())
+            } else {
+                Err(HandlerConflict::Replaced)
+            }
+        }
+        _ => foreign(current),
+    };
+    Ok(res)
+}
+
+/// Check a disposition that isn't the handler of this crate.
+fn foreign(handler: libc::sighandler_t) -> Result<(), HandlerConflict> {
+    if handler == libc::SIG_DFL {
+        Ok(())
+    } else if handler == libc::SIG_IGN {
+        Err(HandlerConflict::Ignored)
+    } else {
+        Err(HandlerConflict::Foreign)
+    }
+}
+
+/// Remove the ctrl+c handler, as well as the handlers of the other signals
+/// listened for, e.g. with `signal_as_error`, and restore the dispositions
+/// from before they were installed.
+///
+/// The wrappers alive at this point stop being interrupted. The handlers are
+/// installed again by the next wrappers that start listening, so that hosts
+/// unloading and reloading this functionality get ctrl+c handling back.
+///
+/// Does nothing for the handlers that were never installed. If a disposition
+/// cannot be restored, the others are restored anyway and the first error is
+/// returned. The handlers that failed are left in place, so that calling it
+/// again retries them.
+pub fn uninstall() -> io::Result<()> {
+    restore(&mut SAVED.lock().unwrap())
+}
+
+fn restore(saved: &mut [Saved]) -> io::Result<()> {
+    let mut res = Ok(());
+    for saved in saved.iter_mut().filter(|saved| !saved.uninstalled) {
+        if let Err(err) = set(saved.signum, &saved.previous) {
+            res = res.and(Err(err));
+            continue;
+        }
+        saved.uninstalled = true;
+        if saved.signum == libc::SIGINT {
+            RECORDED.store(false, Ordering::Release);
+            listener::set_installed(false);
+        }
+    }
+    res
+}
+
+/// Inspect the disposition of `signum` before this crate installs its
+/// handler.
+pub(crate) fn before_install(signum: libc::c_int) {
+    if signum == libc::SIGINT && RECORDED.load(Ordering::Acquire) {
+        return;
+    }
+    let mut saved = SAVED.lock().unwrap();
+    if saved.iter().any(|saved| saved.signum == signum) {
+        return;
+    }
+    let previous = current(signum);
+    if let Ok(previous) = previous {
+        saved.push(Saved {
+            signum,
+            previous,
+            ours: None,
+            uninstalled: false,
+        });
+    }
+    drop(saved);
+    if signum != libc::SIGINT {
+        return;
+    }
+    // Diagnosed before `record_signals`, which installs the handler of
+    // signal-hook-registry in place of the previous one
+    let conflict = previous.map(|previous| foreign(previous.sa_sigaction));
+    record_signals();
+    if let Ok(Err(_conflict)) = conflict {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(target: "tokio_ctrlc_error", conflict = %_conflict, "installing ctrl+c handler");
+    }
+}
+
+/// Record every SIGINT, for `InterruptOptions::ignore_own_signals` and
+/// `exit_on_unhandled_ctrlc`.
+///
+/// tokio-signal registers its handler through signal-hook-registry too, which
+/// runs the actions in the order of registration. This one is registered
+/// first, so that the sender is known by the time the listeners are woken up.
+/// It stays registered until the process exits, so that a ctrl+c is recorded
+/// even when no wrapper is alive, e.g. during the teardown after an error.
+fn record_signals() {
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(|| {
+        // Without the sender, all the signals are treated as external
+        let _ = unsafe {
+            signal_hook_registry::register_sigaction(libc::SIGINT, |info| {
+                SENDER.store(info.si_pid(), Ordering::SeqCst);
+                if listener::active() == 0 {
+                    UNHANDLED.fetch_add(1, Ordering::SeqCst);
+                    if EXIT_ON_UNHANDLED.load(Ordering::SeqCst) {
+                        libc::_exit(SIGINT_EXIT_CODE);
+                    }
+                }
+            })
+        };
+    });
+}
+
+/// Exit right away, with the code 130, on a ctrl+c received while no wrapper
+/// is alive.
+///
+/// Once installed, the ctrl+c handler stays in place until the process exits,
+/// so by default such a ctrl+c is only recorded, see
+/// `Status::unhandled_interrupts`. This allows the user to abort a teardown
+/// that takes too long, e.g. after the main future was interrupted. Nothing is
+/// cleaned up, the process exits from the signal handler.
+pub fn exit_on_unhandled_ctrlc(exit: bool) {
+    EXIT_ON_UNHANDLED.store(exit, Ordering::SeqCst);
+}
+
+pub(crate) fn unhandled() -> usize {
+    UNHANDLED.load(Ordering::SeqCst)
+}
+
+/// Whether the last SIGINT was sent by this process to itself.
+pub(crate) fn sent_by_self() -> bool {
+    SENDER.load(Ordering::SeqCst) == unsafe { libc::getpid() }
+}
+
+/// Record the handler of `signum` this crate has just installed, or put it
+/// back in place after `uninstall`.
+pub(crate) fn installed(signum: libc::c_int) {
+    if signum == libc::SIGINT && RECORDED.load(Ordering::Acquire) {
+        return;
+    }
+    let mut saved = SAVED.lock().unwrap();
+    let saved = match saved.iter_mut().find(|saved| saved.signum == signum) {
+        Some(saved) => saved,
+        None => return,
+    };
+    match saved.ours {
+        None => saved.ours = current(signum).ok(),
+        // The handler is installed only once per process, so after
+        // `uninstall` it has to be put back manually
+        Some(ref ours) if saved.uninstalled => saved.uninstalled = set(signum, ours).is_err(),
+        Some(_) => (),
+    }
+    if signum == libc::SIGINT {
+        let recorded = saved.ours.is_some() && !saved.uninstalled;
+        RECORDED.store(recorded, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_foreign() {
+        assert_eq!(foreign(libc::SIG_DFL), Ok(()));
+        assert_eq!(foreign(libc::SIG_IGN), Err(HandlerConflict::Ignored));
+        let handler = record_signals as fn() as libc::sighandler_t;
+        assert_eq!(foreign(handler), Err(HandlerConflict::Foreign));
+    }
+
+    #[test]
+    fn test_partial_restore() {
+        let saved = |signum| Saved {
+            signum,
+            previous: current(libc::SIGUSR2).unwrap(),
+            ours: None,
+            uninstalled: false,
+        };
+        // The disposition of SIGKILL cannot be changed
+        let mut saved = [saved(libc::SIGKILL), saved(libc::SIGUSR2)];
+        assert!(restore(&mut saved).is_err());
+        assert!(!saved[0].uninstalled);
+        assert!(saved[1].uninstalled);
+    }
+}
@@ -0,0 +1,1194 @@
+use std::{
+    cmp,
+    convert::TryFrom,
+    error::Error,
+    fmt, io,
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+#[cfg(unix)]
+pub(crate) const SIGINT_EXIT_CODE: i32 = 128 + 2;
+// STATUS_CONTROL_C_EXIT
+#[cfg(not(unix))]
+const SIGINT_EXIT_CODE: i32 = 0xC000_013Au32 as i32;
+
+/// The kind of an error produced by this crate, for matching without downcasting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum InterruptKind {
+    /// The user pressed ctrl+c, see `KeyboardInterrupt`.
+    UserInterrupt,
+    /// The ctrl+c handler failed, see `IoError`.
+    HandlerIo,
+    /// The timer driving a delay of this crate failed, see `IoError::is_timer`.
+    Timer,
+    /// The end of stdin was reached, see `EndOfInput`.
+    #[cfg(feature = "terminal")]
+    EndOfInput,
+    /// A signal other than SIGINT was received, see `SignalInterrupt`.
+    #[cfg(unix)]
+    Signal(libc::c_int),
+    /// A trigger other than a signal fired, see `TriggerInterrupt`.
+    Trigger,
+    /// The deadline of `ctrlc_or_timeout` elapsed, see `DeadlineExceeded`.
+    Deadline,
+    /// A console event other than ctrl+c was received, see `ConsoleInterrupt`.
+    #[cfg(windows)]
+    Console(ConsoleEvent),
+}
+
+impl InterruptKind {
+    /// Returns the kind of the first error in the cause chain of `err`
+    /// that was produced by this crate.
+    pub fn of(err: &(dyn Error + 'static)) -> Option<Self> {
+        let mut err = Some(err);
+        while let Some(e) = err {
+            if let Some(kind) = Self::of_single(e) {
+                return Some(kind);
+            }
+            err = next_cause(e);
+        }
+        None
+    }
+
+    /// Like `of`, but for errors wrapped in `failure::Error`.
+    #[cfg(feature = "failure")]
+    pub fn of_failure(err: &failure::Error) -> Option<Self> {
+        err.iter_chain().find_map(|fail| {
+            if fail.downcast_ref::<KeyboardInterrupt>().is_some()
+                || fail.downcast_ref::<StageInterrupted>().is_some()
+            {
+                Some(InterruptKind::UserInterrupt)
+            } else if let Some(err) = fail.downcast_ref::<IoError>() {
+                Some(err.interrupt_kind())
+            } else if fail.downcast_ref::<TriggerInterrupt>().is_some() {
+                Some(InterruptKind::Trigger)
+            } else if fail.downcast_ref::<DeadlineExceeded>().is_some() {
+                Some(InterruptKind::Deadline)
+            } else {
+                #[cfg(feature = "terminal")]
+                {
+                    if fail.downcast_ref::<EndOfInput>().is_some() {
+                        return Some(InterruptKind::EndOfInput);
+                    }
+                }
+                #[cfg(unix)]
+                {
+                    if let Some(interrupt) = fail.downcast_ref::<SignalInterrupt>() {
+                        return Some(interrupt.interrupt_kind());
+                    }
+                }
+                #[cfg(windows)]
+                {
+                    if let Some(interrupt) = fail.downcast_ref::<ConsoleInterrupt>() {
+                        return Some(interrupt.interrupt_kind());
+                    }
+                }
+                None
+            }
+        })
+    }
+
+    /// Like `of`, but for errors wrapped in `anyhow::Error`.
+    #[cfg(feature = "anyhow")]
+    pub fn of_anyhow(err: &anyhow::Error) -> Option<Self> {
+        Self::of(err.as_ref())
+    }
+
+    /// The conventional exit code of a process that stopped because of this
+    /// kind of error.
+    ///
+    /// Interrupts map to what a shell reports for a process killed by the
+    /// signal: 130 (128 + SIGINT) on Unix and `STATUS_CONTROL_C_EXIT` on
+    /// Windows. Handler and timer failures map to 1 and the end of input to 0, as it is
+    /// the usual way to leave an interactive program. Other signals map to 128
+    /// plus the signal number, e.g. 143 for SIGTERM, and triggers to 1.
+    /// Deadlines map to 124, like for the `timeout` command.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            InterruptKind::UserInterrupt => SIGINT_EXIT_CODE,
+            InterruptKind::HandlerIo | InterruptKind::Timer => 1,
+            #[cfg(feature = "terminal")]
+            InterruptKind::EndOfInput => 0,
+            #[cfg(unix)]
+            InterruptKind::Signal(signal) => 128 + signal,
+            InterruptKind::Trigger => 1,
+            InterruptKind::Deadline => 124,
+            #[cfg(windows)]
+            InterruptKind::Console(_) => SIGINT_EXIT_CODE,
+        }
+    }
+
+    /// The signal this kind of interrupt is caused by: SIGINT for user
+    /// interrupts and the signal received for `Signal`. `None` for the other
+    /// kinds.
+    #[cfg(unix)]
+    pub fn signal(self) -> Option<libc::c_int> {
+        match self {
+            InterruptKind::UserInterrupt => Some(libc::SIGINT),
+            InterruptKind::Signal(signal) => Some(signal),
+            _ => None,
+        }
+    }
+
+    fn of_single(err: &(dyn Error + 'static)) -> Option<Self> {
+        if err.is::<KeyboardInterrupt>() || err.is::<StageInterrupted>() {
+            Some(InterruptKind::UserInterrupt)
+        } else if let Some(err) = err.downcast_ref::<IoError>() {
+            Some(err.interrupt_kind())
+        } else if err.is::<TriggerInterrupt>() {
+            Some(InterruptKind::Trigger)
+        } else if err.is::<DeadlineExceeded>() {
+            Some(InterruptKind::Deadline)
+        } else {
+            #[cfg(feature = "terminal")]
+            {
+                if err.is::<EndOfInput>() {
+                    return Some(InterruptKind::EndOfInput);
+                }
+            }
+            #[cfg(unix)]
+            {
+                if let Some(interrupt) = err.downcast_ref::<SignalInterrupt>() {
+                    return Some(interrupt.interrupt_kind());
+                }
+            }
+            #[cfg(windows)]
+            {
+                if let Some(interrupt) = err.downcast_ref::<ConsoleInterrupt>() {
+                    return Some(interrupt.interrupt_kind());
+                }
+            }
+            None
+        }
+    }
+}
+
+/// Converts to `InterruptKind::exit_code`, for `fn main() -> ExitCode`.
+///
+/// `ExitCode` can only be built from a `u8`, so the codes that don't fit,
+/// like `STATUS_CONTROL_C_EXIT` on Windows, become `ExitCode::FAILURE`.
+impl From<InterruptKind> for ExitCode {
+    fn from(kind: InterruptKind) -> Self {
+        u8::try_from(kind.exit_code()).map_or(ExitCode::FAILURE, ExitCode::from)
+    }
+}
+
+/// The common interface of the errors telling the program to stop.
+///
+/// Interactive interrupts come from the user at the terminal, so it may be
+/// appropriate to e.g. ask them for confirmation. Other interrupts come from
+/// the environment, e.g. a service manager, and nobody is there to answer.
+pub trait InterruptLike: Error + Send + Sync + 'static {
+    /// Whether the interrupt was requested interactively by the user.
+    fn is_interactive(&self) -> bool;
+
+    /// The kind of the interrupt, for matching without downcasting.
+    fn interrupt_kind(&self) -> InterruptKind;
+
+    /// The recommended exit code, see `InterruptKind::exit_code`.
+    fn exit_code(&self) -> i32 {
+        self.interrupt_kind().exit_code()
+    }
+}
+
+/// Returns the first interrupt in the cause chain of `err`.
+#[cfg(feature = "failure")]
+pub fn find_interrupt(err: &failure::Error) -> Option<&dyn InterruptLike> {
+    err.iter_chain().find_map(|fail| {
+        if let Some(interrupt) = fail.downcast_ref::<KeyboardInterrupt>() {
+            return Some(interrupt as &dyn InterruptLike);
+        }
+        if let Some(interrupt) = fail.downcast_ref::<StageInterrupted>() {
+            return Some(interrupt as &dyn InterruptLike);
+        }
+        if let Some(interrupt) = fail.downcast_ref::<TriggerInterrupt>() {
+            return Some(interrupt as &dyn InterruptLike);
+        }
+        if let Some(interrupt) = fail.downcast_ref::<DeadlineExceeded>() {
+            return Some(interrupt as &dyn InterruptLike);
+        }
+        #[cfg(feature = "terminal")]
+        {
+            if let Some(eof) = fail.downcast_ref::<EndOfInput>() {
+                return Some(eof as &dyn InterruptLike);
+            }
+        }
+        #[cfg(unix)]
+        {
+            if let Some(interrupt) = fail.downcast_ref::<SignalInterrupt>() {
+                return Some(interrupt as &dyn InterruptLike);
+            }
+        }
+        #[cfg(windows)]
+        {
+            if let Some(interrupt) = fail.downcast_ref::<ConsoleInterrupt>() {
+                return Some(interrupt as &dyn InterruptLike);
+            }
+        }
+        None
+    })
+}
+
+/// Like `find_interrupt`, but for the `source` chain of a `std::error::Error`.
+pub fn find_interrupt_std<'a>(err: &'a (dyn Error + 'static)) -> Option<&'a dyn InterruptLike> {
+    let mut err = Some(err);
+    while let Some(e) = err {
+        if let Some(interrupt) = e.downcast_ref::<KeyboardInterrupt>() {
+            return Some(interrupt);
+        }
+        if let Some(interrupt) = e.downcast_ref::<StageInterrupted>() {
+            return Some(interrupt);
+        }
+        if let Some(interrupt) = e.downcast_ref::<TriggerInterrupt>() {
+            return Some(interrupt);
+        }
+        if let Some(interrupt) = e.downcast_ref::<DeadlineExceeded>() {
+            return Some(interrupt);
+        }
+        #[cfg(feature = "terminal")]
+        {
+            if let Some(eof) = e.downcast_ref::<EndOfInput>() {
+                return Some(eof);
+            }
+        }
+        #[cfg(unix)]
+        {
+            if let Some(interrupt) = e.downcast_ref::<SignalInterrupt>() {
+                return Some(interrupt);
+            }
+        }
+        #[cfg(windows)]
+        {
+            if let Some(interrupt) = e.downcast_ref::<ConsoleInterrupt>() {
+                return Some(interrupt);
+            }
+        }
+        err = next_cause(e);
+    }
+    None
+}
+
+/// The cause of `err`, including the error wrapped by an `io::Error`, which
+/// `source` skips, e.g. the one returned by `ctrlc_read`.
+fn next_cause<'a>(err: &'a (dyn Error + 'static)) -> Option<&'a (dyn Error + 'static)> {
+    match err.downcast_ref::<io::Error>().and_then(|e| e.get_ref()) {
+        Some(inner) => Some(inner as &(dyn Error + 'static)),
+        None => err.source(),
+    }
+}
+
+/// Like `find_interrupt`, but for errors wrapped in `anyhow::Error`.
+#[cfg(feature = "anyhow")]
+pub fn find_interrupt_anyhow(err: &anyhow::Error) -> Option<&dyn InterruptLike> {
+    find_interrupt_std(err.as_ref())
+}
+
+/// The interrupts seen by a wrapper, shared with the errors it returned.
+#[derive(Debug)]
+pub(crate) struct Received {
+    /// The number of interrupts the wrapper has seen so far.
+    pub(crate) count: AtomicUsize,
+    /// The interrupts seen by the wrapper and by the shared listener when
+    /// the error was returned, to count the later ones once the wrapper is
+    /// gone.
+    pub(crate) shared: Option<(usize, usize)>,
+    pub(crate) first: Instant,
+    pub(crate) first_at: SystemTime,
+    pub(crate) signal: Option<&'static str>,
+}
+
+impl Received {
+    fn count(&self) -> usize {
+        let seen = self.count.load(Ordering::Acquire);
+        match self.shared {
+            Some((base, since)) => cmp::max(seen, base + crate::shared::received() - since),
+            None => seen,
+        }
+    }
+}
+
+/// The error returned when the user presses ctrl+c.
+///
+/// The errors returned by the wrappers tell how many interrupts were received
+/// and when, e.g. to debug a shutdown that takes too long.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct KeyboardInterrupt {
+    received: Option<Arc<Received>>,
+}
+
+impl KeyboardInterrupt {
+    /// An interrupt without any details, e.g. for the errors of job checks.
+    pub fn new() -> Self {
+        KeyboardInterrupt { received: None }
+    }
+
+    pub(crate) fn received(received: Arc<Received>) -> Self {
+        KeyboardInterrupt {
+            received: Some(received),
+        }
+    }
+
+    /// The number of interrupts received, or `None` for `new` errors.
+    ///
+    /// The count keeps growing while the program cleans up after the error:
+    /// the shared ctrl+c listener keeps counting once the wrapper is gone.
+    /// With a `reactor` or a custom signal source, only the interrupts seen
+    /// by the wrapper while it's polled are counted.
+    pub fn count(&self) -> Option<usize> {
+        self.received.as_ref().map(|received| received.count())
+    }
+
+    /// When the first interrupt was received.
+    pub fn first_received(&self) -> Option<Instant> {
+        self.received.as_ref().map(|received| received.first)
+    }
+
+    /// When the first interrupt was received, as wall-clock time for logs.
+    pub fn first_received_at(&self) -> Option<SystemTime> {
+        self.received.as_ref().map(|received| received.first_at)
+    }
+
+    /// The signal received: `SIGINT` on Unix and `CTRL_C_EVENT` on Windows,
+    /// or the signal listened for instead, e.g. `SIGTERM`. `None` for custom
+    /// signal sources, uncommon signals and `new` errors.
+    pub fn signal(&self) -> Option<&'static str> {
+        self.received.as_ref().and_then(|received| received.signal)
+    }
+
+    /// Always `InterruptKind::UserInterrupt`.
+    pub fn interrupt_kind(&self) -> InterruptKind {
+        InterruptKind::UserInterrupt
+    }
+
+    /// The recommended exit code, see `InterruptKind::exit_code`.
+    pub fn exit_code(&self) -> i32 {
+        self.interrupt_kind().exit_code()
+    }
+}
+
+impl fmt::Display for KeyboardInterrupt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("keyboard interrupt")
+    }
+}
+
+impl Error for KeyboardInterrupt {}
+
+impl InterruptLike for KeyboardInterrupt {
+    fn is_interactive(&self) -> bool {
+        true
+    }
+
+    fn interrupt_kind(&self) -> InterruptKind {
+        KeyboardInterrupt::interrupt_kind(self)
+    }
+
+    fn exit_code(&self) -> i32 {
+        KeyboardInterrupt::exit_code(self)
+    }
+}
+
+impl<'a> From<&'a KeyboardInterrupt> for ExitCode {
+    fn from(err: &'a KeyboardInterrupt) -> Self {
+        err.interrupt_kind().into()
+    }
+}
+
+/// The error returned when a `Pipeline` is interrupted by ctrl+c.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageInterrupted {
+    stage: &'static str,
+}
+
+impl StageInterrupted {
+    pub fn new(stage: &'static str) -> Self {
+        StageInterrupted { stage }
+    }
+
+    /// The name of the interrupted stage: the one that was running if it was
+    /// interruptible, or else the one that was about to start.
+    pub fn stage(&self) -> &'static str {
+        self.stage
+    }
+
+    /// Always `InterruptKind::UserInterrupt`, like for `KeyboardInterrupt`.
+    pub fn interrupt_kind(&self) -> InterruptKind {
+        InterruptKind::UserInterrupt
+    }
+}
+
+impl fmt::Display for StageInterrupted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "keyboard interrupt at stage `{}`", self.stage)
+    }
+}
+
+impl Error for StageInterrupted {}
+
+impl InterruptLike for StageInterrupted {
+    fn is_interactive(&self) -> bool {
+        true
+    }
+
+    fn interrupt_kind(&self) -> InterruptKind {
+        StageInterrupted::interrupt_kind(self)
+    }
+}
+
+impl<'a> From<&'a StageInterrupted> for ExitCode {
+    fn from(err: &'a StageInterrupted) -> Self {
+        err.interrupt_kind().into()
+    }
+}
+
+/// The error returned when a trigger added with `interrupt_on` fires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriggerInterrupt {
+    trigger: &'static str,
+}
+
+impl TriggerInterrupt {
+    pub fn new(trigger: &'static str) -> Self {
+        TriggerInterrupt { trigger }
+    }
+
+    /// The name the trigger was added with.
+    pub fn trigger(&self) -> &'static str {
+        self.trigger
+    }
+
+    /// Always `InterruptKind::Trigger`.
+    pub fn interrupt_kind(&self) -> InterruptKind {
+        InterruptKind::Trigger
+    }
+}
+
+impl fmt::Display for TriggerInterrupt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "interrupted by trigger `{}`", self.trigger)
+    }
+}
+
+impl Error for TriggerInterrupt {}
+
+impl InterruptLike for TriggerInterrupt {
+    fn is_interactive(&self) -> bool {
+        false
+    }
+
+    fn interrupt_kind(&self) -> InterruptKind {
+        TriggerInterrupt::interrupt_kind(self)
+    }
+}
+
+impl<'a> From<&'a TriggerInterrupt> for ExitCode {
+    fn from(err: &'a TriggerInterrupt) -> Self {
+        err.interrupt_kind().into()
+    }
+}
+
+/// The error returned when the deadline of `ctrlc_or_timeout` elapses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadlineExceeded {
+    timeout: Duration,
+}
+
+impl DeadlineExceeded {
+    pub fn new(timeout: Duration) -> Self {
+        DeadlineExceeded { timeout }
+    }
+
+    /// The timeout the future was given.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Always `InterruptKind::Deadline`.
+    pub fn interrupt_kind(&self) -> InterruptKind {
+        InterruptKind::Deadline
+    }
+}
+
+impl fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "deadline exceeded after {:?}", self.timeout)
+    }
+}
+
+impl Error for DeadlineExceeded {}
+
+impl InterruptLike for DeadlineExceeded {
+    fn is_interactive(&self) -> bool {
+        false
+    }
+
+    fn interrupt_kind(&self) -> InterruptKind {
+        DeadlineExceeded::interrupt_kind(self)
+    }
+}
+
+impl<'a> From<&'a DeadlineExceeded> for ExitCode {
+    fn from(err: &'a DeadlineExceeded) -> Self {
+        err.interrupt_kind().into()
+    }
+}
+
+/// The error returned when the end of stdin is reached, e.g. when the user
+/// presses ctrl+d.
+#[cfg(feature = "terminal")]
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct EndOfInput;
+
+#[cfg(feature = "terminal")]
+impl EndOfInput {
+    pub fn new() -> Self {
+        EndOfInput
+    }
+
+    /// Always `InterruptKind::EndOfInput`.
+    pub fn interrupt_kind(&self) -> InterruptKind {
+        InterruptKind::EndOfInput
+    }
+}
+
+#[cfg(feature = "terminal")]
+impl fmt::Display for EndOfInput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("end of input")
+    }
+}
+
+#[cfg(feature = "terminal")]
+impl Error for EndOfInput {}
+
+#[cfg(feature = "terminal")]
+impl InterruptLike for EndOfInput {
+    fn is_interactive(&self) -> bool {
+        true
+    }
+
+    fn interrupt_kind(&self) -> InterruptKind {
+        EndOfInput::interrupt_kind(self)
+    }
+}
+
+#[cfg(feature = "terminal")]
+impl<'a> From<&'a EndOfInput> for ExitCode {
+    fn from(err: &'a EndOfInput) -> Self {
+        err.interrupt_kind().into()
+    }
+}
+
+/// The error returned by `signal_as_error` when one of the signals is
+/// received.
+#[cfg(unix)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalInterrupt {
+    signal: libc::c_int,
+}
+
+#[cfg(unix)]
+impl SignalInterrupt {
+    pub fn new(signal: libc::c_int) -> Self {
+        SignalInterrupt { signal }
+    }
+
+    /// The number of the signal that was received, e.g. `libc::SIGTERM`.
+    pub fn signal(&self) -> libc::c_int {
+        self.signal
+    }
+
+    /// `InterruptKind::Signal` with the signal, or `UserInterrupt` for SIGINT.
+    pub fn interrupt_kind(&self) -> InterruptKind {
+        if self.signal == libc::SIGINT {
+            InterruptKind::UserInterrupt
+        } else {
+            InterruptKind::Signal(self.signal)
+        }
+    }
+}
+
+#[cfg(unix)]
+impl fmt::Display for SignalInterrupt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match signal_name(self.signal) {
+            Some(name) => write!(f, "interrupted by {}", name),
+            None => write!(f, "interrupted by signal {}", self.signal),
+        }
+    }
+}
+
+/// The common signals, with their names.
+#[cfg(unix)]
+const SIGNALS: [(libc::c_int, &str); 6] = [
+    (libc::SIGHUP, "SIGHUP"),
+    (libc::SIGINT, "SIGINT"),
+    (libc::SIGQUIT, "SIGQUIT"),
+    (libc::SIGTERM, "SIGTERM"),
+    (libc::SIGUSR1, "SIGUSR1"),
+    (libc::SIGUSR2, "SIGUSR2"),
+];
+
+/// The name of the signal, for the common ones.
+#[cfg(unix)]
+pub(crate) fn signal_name(signal: libc::c_int) -> Option<&'static str> {
+    SIGNALS
+        .iter()
+        .find(|&&(signum, _)| signum == signal)
+        .map(|&(_, name)| name)
+}
+
+/// The number of one of the common signals, named e.g. `SIGTERM` or `term`.
+#[cfg(all(unix, feature = "clap"))]
+pub(crate) fn signal_number(name: &str) -> Option<libc::c_int> {
+    let name = name.to_ascii_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+    SIGNALS
+        .iter()
+        .find(|&&(_, signal)| signal[3..] == *name)
+        .map(|&(signum, _)| signum)
+}
+
+#[cfg(unix)]
+impl Error for SignalInterrupt {}
+
+#[cfg(unix)]
+impl InterruptLike for SignalInterrupt {
+    fn is_interactive(&self) -> bool {
+        self.signal == libc::SIGINT
+    }
+
+    fn interrupt_kind(&self) -> InterruptKind {
+        SignalInterrupt::interrupt_kind(self)
+    }
+}
+
+#[cfg(unix)]
+impl<'a> From<&'a SignalInterrupt> for ExitCode {
+    fn from(err: &'a SignalInterrupt) -> Self {
+        err.interrupt_kind().into()
+    }
+}
+
+/// The error returned when the ctrl+c handler could not be set up or polled.
+#[derive(Debug)]
+pub struct IoError(io::Error);
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_timer() {
+            write!(f, "timer error handling ctrl+c: {}", self.0)
+        } else {
+            write!(f, "I/O error handling ctrl+c: {}", self.0)
+        }
+    }
+}
+
+impl IoError {
+    /// The error of a `Delay` used by this crate, e.g. when the future isn't
+    /// run on a runtime with a timer.
+    pub(crate) fn timer(err: tokio_timer::Error) -> Self {
+        IoError(io::Error::other(err))
+    }
+
+    /// Returns whether the error comes from the timer rather than from the
+    /// ctrl+c handler, e.g. when the future isn't run on a runtime with a
+    /// timer.
+    pub fn is_timer(&self) -> bool {
+        self.0
+            .get_ref()
+            .is_some_and(|err| err.is::<tokio_timer::Error>())
+    }
+
+    /// Returns the kind of the underlying I/O error, e.g. `PermissionDenied`
+    /// when signal handling is forbidden by a sandbox.
+    pub fn kind(&self) -> io::ErrorKind {
+        self.0.kind()
+    }
+
+    /// Returns a reference to the underlying I/O error.
+    pub fn get_ref(&self) -> &io::Error {
+        &self.0
+    }
+
+    /// Consumes the error, returning the underlying I/O error.
+    pub fn into_inner(self) -> io::Error {
+        self.0
+    }
+
+    /// `InterruptKind::Timer` for timer errors, `InterruptKind::HandlerIo`
+    /// otherwise.
+    pub fn interrupt_kind(&self) -> InterruptKind {
+        if self.is_timer() {
+            InterruptKind::Timer
+        } else {
+            InterruptKind::HandlerIo
+        }
+    }
+}
+
+impl From<io::Error> for IoError {
+    fn from(err: io::Error) -> Self {
+        IoError(err)
+    }
+}
+
+impl<'a> From<&'a IoError> for ExitCode {
+    fn from(err: &'a IoError) -> Self {
+        err.interrupt_kind().into()
+    }
+}
+
+impl Error for IoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+/// A Windows console event.
+#[cfg(windows)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ConsoleEvent {
+    /// The user pressed ctrl+c.
+    CtrlC,
+    /// The user pressed ctrl+break.
+    CtrlBreak,
+    /// The console window is being closed.
+    Close,
+}
+
+/// The error returned when `console_as_error` receives a console event.
+///
+/// When both ctrl+c and ctrl+break are intercepted, `event` tells them apart,
+/// e.g. to stop gracefully on ctrl+c but abort right away on ctrl+break:
+/// ```no_run
+///     use tokio_ctrlc_error::{AsyncConsole, ConsoleEvent, ConsoleInterrupt};
+///
+///     let task = futures::future::empty::<(), failure::Error>()
+///         .console_as_error(&[ConsoleEvent::CtrlC, ConsoleEvent::CtrlBreak]);
+///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+///     let err = rt.block_on(task).unwrap_err();
+///     match err.downcast_ref::<ConsoleInterrupt>().map(ConsoleInterrupt::event) {
+///         Some(ConsoleEvent::CtrlBreak) => std::process::abort(),
+///         _ => println!("stopping"),
+///     }
+/// ```
+#[cfg(windows)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsoleInterrupt {
+    event: ConsoleEvent,
+}
+
+#[cfg(windows)]
+impl ConsoleInterrupt {
+    pub fn new(event: ConsoleEvent) -> Self {
+        ConsoleInterrupt { event }
+    }
+
+    /// The event that was received.
+    pub fn event(&self) -> ConsoleEvent {
+        self.event
+    }
+
+    /// `InterruptKind::Console` with the event, or `UserInterrupt` for ctrl+c.
+    pub fn interrupt_kind(&self) -> InterruptKind {
+        match self.event {
+            ConsoleEvent::CtrlC => InterruptKind::UserInterrupt,
+            event => InterruptKind::Console(event),
+        }
+    }
+}
+
+#[cfg(windows)]
+impl fmt::Display for ConsoleInterrupt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.event {
+            ConsoleEvent::CtrlC => f.write_str("keyboard interrupt"),
+            ConsoleEvent::CtrlBreak => f.write_str("interrupted by ctrl+break"),
+            ConsoleEvent::Close => f.write_str("interrupted by closing the console"),
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Error for ConsoleInterrupt {}
+
+#[cfg(windows)]
+impl InterruptLike for ConsoleInterrupt {
+    fn is_interactive(&self) -> bool {
+        self.event != ConsoleEvent::Close
+    }
+
+    fn interrupt_kind(&self) -> InterruptKind {
+        ConsoleInterrupt::interrupt_kind(self)
+    }
+}
+
+#[cfg(windows)]
+impl<'a> From<&'a ConsoleInterrupt> for ExitCode {
+    fn from(err: &'a ConsoleInterrupt) -> Self {
+        err.interrupt_kind().into()
+    }
+}
+
+#[cfg(feature = "miette")]
+mod diagnostic {
+    use super::{IoError, KeyboardInterrupt};
+    use miette::{Diagnostic, Severity};
+    use std::fmt::Display;
+
+    impl Diagnostic for KeyboardInterrupt {
+        fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            Some(Box::new("ctrlc::interrupted"))
+        }
+
+        fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            Some(Box::new("the operation was cancelled by the user"))
+        }
+
+        fn severity(&self) -> Option<Severity> {
+            Some(Severity::Warning)
+        }
+    }
+
+    impl Diagnostic for super::StageInterrupted {
+        fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            Some(Box::new("ctrlc::interrupted"))
+        }
+
+        fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            Some(Box::new(format!(
+                "the pipeline was cancelled by the user at stage `{}`",
+                self.stage()
+            )))
+        }
+
+        fn severity(&self) -> Option<Severity> {
+            Some(Severity::Warning)
+        }
+    }
+
+    impl Diagnostic for super::TriggerInterrupt {
+        fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            Some(Box::new("ctrlc::trigger"))
+        }
+
+        fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            Some(Box::new(format!(
+                "the trigger `{}` asked the program to stop",
+                self.trigger()
+            )))
+        }
+
+        fn severity(&self) -> Option<Severity> {
+            Some(Severity::Warning)
+        }
+    }
+
+    impl Diagnostic for super::DeadlineExceeded {
+        fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            Some(Box::new("ctrlc::deadline"))
+        }
+
+        fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            Some(Box::new(
+                "the operation took too long; give it a longer timeout if that's expected",
+            ))
+        }
+
+        fn severity(&self) -> Option<Severity> {
+            Some(Severity::Error)
+        }
+    }
+
+    #[cfg(feature = "terminal")]
+    impl Diagnostic for super::EndOfInput {
+        fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            Some(Box::new("ctrlc::eof"))
+        }
+
+        fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            Some(Box::new("the input was closed, e.g. with ctrl+d"))
+        }
+
+        fn severity(&self) -> Option<Severity> {
+            Some(Severity::Advice)
+        }
+    }
+
+    #[cfg(windows)]
+    impl Diagnostic for super::ConsoleInterrupt {
+        fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            Some(Box::new("ctrlc::console"))
+        }
+
+        fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            Some(Box::new("the console asked the program to stop"))
+        }
+
+        fn severity(&self) -> Option<Severity> {
+            Some(Severity::Warning)
+        }
+    }
+
+    #[cfg(unix)]
+    impl Diagnostic for super::SignalInterrupt {
+        fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            Some(Box::new("ctrlc::signal"))
+        }
+
+        fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            Some(Box::new(
+                "the process was asked to stop, e.g. by a service manager",
+            ))
+        }
+
+        fn severity(&self) -> Option<Severity> {
+            Some(Severity::Warning)
+        }
+    }
+
+    impl Diagnostic for IoError {
+        fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            if self.is_timer() {
+                Some(Box::new("ctrlc::timer"))
+            } else {
+                Some(Box::new("ctrlc::io"))
+            }
+        }
+
+        fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            if self.is_timer() {
+                Some(Box::new(
+                    "the timer failed; run the future on a tokio runtime with a timer",
+                ))
+            } else {
+                Some(Box::new(
+                    "the ctrl+c handler could not be installed; check whether signals are allowed",
+                ))
+            }
+        }
+
+        fn severity(&self) -> Option<Severity> {
+            Some(Severity::Error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_interrupt_std, InterruptKind, IoError, KeyboardInterrupt};
+    use std::{error::Error, fmt, io};
+
+    #[test]
+    fn test_display() {
+        assert_eq!(KeyboardInterrupt::new().to_string(), "keyboard interrupt");
+        let err = IoError::from(io::Error::other("oops"));
+        assert_eq!(err.to_string(), "I/O error handling ctrl+c: oops");
+        assert!(err.source().is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exit_code() {
+        assert_eq!(KeyboardInterrupt::new().exit_code(), 130);
+        assert_eq!(InterruptKind::HandlerIo.exit_code(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_signal() {
+        assert_eq!(InterruptKind::UserInterrupt.signal(), Some(libc::SIGINT));
+        let kind = InterruptKind::Signal(libc::SIGTERM);
+        assert_eq!(kind.signal(), Some(libc::SIGTERM));
+        assert_eq!(InterruptKind::Deadline.signal(), None);
+    }
+
+    #[test]
+    fn test_process_exit_code() {
+        use std::process::ExitCode;
+
+        let code = ExitCode::from(&KeyboardInterrupt::new());
+        #[cfg(unix)]
+        assert_eq!(code, ExitCode::from(130));
+        #[cfg(not(unix))]
+        assert_eq!(code, ExitCode::FAILURE);
+        let err = IoError::from(io::Error::from(io::ErrorKind::Other));
+        assert_eq!(ExitCode::from(&err), ExitCode::from(1));
+    }
+
+    #[test]
+    fn test_io_error_inner() {
+        let err = IoError::from(io::Error::from(io::ErrorKind::PermissionDenied));
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(err.get_ref().kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(err.into_inner().kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    // Both types must still be usable with `failure` through its blanket impl
+    #[test]
+    fn test_failure_compat() {
+        let err: failure::Error = KeyboardInterrupt::new().into();
+        assert!(err.downcast_ref::<KeyboardInterrupt>().is_some());
+    }
+
+    #[derive(Debug)]
+    struct Wrapper(KeyboardInterrupt);
+
+    impl fmt::Display for Wrapper {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("wrapper")
+        }
+    }
+
+    impl Error for Wrapper {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn test_kind() {
+        let err = IoError::from(io::Error::other("oops"));
+        assert_eq!(InterruptKind::of(&err), Some(InterruptKind::HandlerIo));
+        let wrapped = Wrapper(KeyboardInterrupt::new());
+        assert_eq!(
+            InterruptKind::of(&wrapped),
+            Some(InterruptKind::UserInterrupt)
+        );
+        assert_eq!(InterruptKind::of(&io::Error::other("oops")), None);
+
+        #[cfg(feature = "failure")]
+        {
+            let err = failure::Error::from(KeyboardInterrupt::new()).context("while sleeping");
+            let err = failure::Error::from(err);
+            assert_eq!(
+                InterruptKind::of_failure(&err),
+                Some(InterruptKind::UserInterrupt)
+            );
+            assert_eq!(InterruptKind::of_failure(&failure::err_msg("oops")), None);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_signal_interrupt() {
+        use super::{InterruptLike, SignalInterrupt};
+
+        let err = SignalInterrupt::new(libc::SIGTERM);
+        assert_eq!(err.to_string(), "interrupted by SIGTERM");
+        assert!(!err.is_interactive());
+        assert_eq!(
+            InterruptKind::of(&err),
+            Some(InterruptKind::Signal(libc::SIGTERM))
+        );
+        assert_eq!(err.exit_code(), 143);
+
+        let err = SignalInterrupt::new(libc::SIGINT);
+        assert!(err.is_interactive());
+        assert_eq!(err.exit_code(), 130);
+    }
+
+    #[cfg(feature = "failure")]
+    #[test]
+    fn test_find_interrupt() {
+        use super::find_interrupt;
+
+        let err = failure::Error::from(KeyboardInterrupt::new()).context("while sleeping");
+        let err = failure::Error::from(err);
+        let interrupt = find_interrupt(&err).unwrap();
+        assert!(interrupt.is_interactive());
+        assert_eq!(interrupt.interrupt_kind(), InterruptKind::UserInterrupt);
+
+        let err = failure::Error::from(IoError::from(io::Error::other("oops")));
+        assert!(find_interrupt(&err).is_none());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_console_interrupt() {
+        use super::{ConsoleEvent, ConsoleInterrupt, InterruptLike};
+
+        let err = ConsoleInterrupt::new(ConsoleEvent::Close);
+        assert_eq!(err.to_string(), "interrupted by closing the console");
+        assert!(!err.is_interactive());
+        assert_eq!(
+            InterruptKind::of(&err),
+            Some(InterruptKind::Console(ConsoleEvent::Close))
+        );
+
+        let err = ConsoleInterrupt::new(ConsoleEvent::CtrlC);
+        assert!(err.is_interactive());
+        assert_eq!(InterruptKind::of(&err), Some(InterruptKind::UserInterrupt));
+
+        let err = ConsoleInterrupt::new(ConsoleEvent::CtrlBreak);
+        assert_eq!(err.to_string(), "interrupted by ctrl+break");
+        assert!(err.is_interactive());
+        assert_eq!(
+            InterruptKind::of(&err),
+            Some(InterruptKind::Console(ConsoleEvent::CtrlBreak))
+        );
+    }
+
+    #[test]
+    fn test_find_interrupt_std() {
+        let wrapped = Wrapper(KeyboardInterrupt::new());
+        let interrupt = find_interrupt_std(&wrapped).unwrap();
+        assert_eq!(interrupt.interrupt_kind(), InterruptKind::UserInterrupt);
+
+        let err: Box<dyn Error + Send + Sync> = IoError::from(io::Error::other("oops")).into();
+        assert!(find_interrupt_std(&*err).is_none());
+    }
+
+    #[cfg(feature = "anyhow")]
+    #[test]
+    fn test_anyhow() {
+        use super::find_interrupt_anyhow;
+
+        let err = anyhow::Error::from(KeyboardInterrupt::new()).context("while sleeping");
+        assert_eq!(
+            InterruptKind::of_anyhow(&err),
+            Some(InterruptKind::UserInterrupt)
+        );
+        assert!(find_interrupt_anyhow(&err).unwrap().is_interactive());
+        assert_eq!(InterruptKind::of_anyhow(&anyhow::anyhow!("oops")), None);
+    }
+
+    #[cfg(feature = "miette")]
+    #[test]
+    fn test_diagnostic() {
+        use miette::{Diagnostic, Severity};
+        use std::time::Duration;
+
+        let code = KeyboardInterrupt::new().code().unwrap().to_string();
+        assert_eq!(code, "ctrlc::interrupted");
+        assert!(KeyboardInterrupt::new().help().is_some());
+        assert_eq!(KeyboardInterrupt::new().severity(), Some(Severity::Warning));
+
+        let errors: Vec<Box<dyn Diagnostic>> = vec![
+            Box::new(KeyboardInterrupt::new()),
+            Box::new(super::StageInterrupted::new("fetch")),
+            Box::new(super::TriggerInterrupt::new("reload")),
+            Box::new(super::DeadlineExceeded::new(Duration::from_secs(1))),
+            #[cfg(feature = "terminal")]
+            Box::new(super::EndOfInput::new()),
+            #[cfg(unix)]
+            Box::new(super::SignalInterrupt::new(libc::SIGTERM)),
+            Box::new(IoError::from(io::Error::other("oops"))),
+        ];
+        for err in errors {
+            assert!(err.code().is_some(), "{}", err);
+            assert!(err.help().is_some(), "{}", err);
+            assert!(err.severity().is_some(), "{}", err);
+        }
+    }
+}
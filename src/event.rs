@@ -0,0 +1,115 @@
+//! Interrupts requested through a named Windows event.
+
+use futures::{prelude::*, sync::mpsc};
+use std::{ffi::OsStr, io, os::windows::ffi::OsStrExt, ptr, thread};
+use windows_sys::Win32::{
+    Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0, WAIT_TIMEOUT},
+    System::Threading::{
+        CreateEventW, OpenEventW, SetEvent, WaitForSingleObject, EVENT_MODIFY_STATE,
+    },
+};
+
+/// How often the waiting thread checks whether the stream was dropped.
+const POLL_INTERVAL_MS: u32 = 100;
+
+struct Handle(HANDLE);
+
+// The handle of an event can be used from any thread
+unsafe impl Send for Handle {}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        unsafe {
+            CloseHandle(self.0);
+        }
+    }
+}
+
+fn wide(name: &str) -> Vec<u16> {
+    OsStr::new(name).encode_wide().chain(Some(0)).collect()
+}
+
+/// Stream returned by `named_event`, yielding an item every time the event is
+/// signaled.
+pub struct NamedEvent(mpsc::UnboundedReceiver<io::Result<()>>);
+
+impl Stream for NamedEvent {
+    type Error = io::Error;
+    type Item = ();
+
+    fn poll(&mut self) -> Poll<Option<()>, io::Error> {
+        match self.0.poll() {
+            Ok(Async::Ready(Some(res))) => res.map(|()| Async::Ready(Some(()))),
+            Ok(Async::Ready(None)) => Ok(Async::Ready(None)),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(()) => Ok(Async::Ready(None)),
+        }
+    }
+}
+
+/// Create, or open if it exists, the named event `name` and listen for it
+/// being signaled, e.g. with `signal_named_event`.
+///
+/// This lets installers, service managers or sibling processes request a
+/// graceful shutdown, the way SIGTERM is used on Unix. Pass the stream to
+/// `CtrlcAsError::with_signal_source`. The name may have a `Global\` or `Local\`
+/// prefix, as usual for kernel objects.
+///
+/// The event is auto-reset: signaling it several times before it is seen
+/// yields a single item. The waiting is done by a thread, which exits shortly
+/// after the stream is dropped.
+pub fn named_event(name: &str) -> io::Result<NamedEvent> {
+    let handle = unsafe { CreateEventW(ptr::null(), 0, 0, wide(name).as_ptr()) };
+    if handle.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    let handle = Handle(handle);
+    let (tx, rx) = mpsc::unbounded();
+    thread::spawn(move || {
+        let handle = handle;
+        while !tx.is_closed() {
+            let res = match unsafe { WaitForSingleObject(handle.0, POLL_INTERVAL_MS) } {
+                WAIT_OBJECT_0 => Ok(()),
+                WAIT_TIMEOUT => continue,
+                _ => Err(io::Error::last_os_error()),
+            };
+            let failed = res.is_err();
+            if tx.unbounded_send(res).is_err() || failed {
+                break;
+            }
+        }
+    });
+    Ok(NamedEvent(rx))
+}
+
+/// Signal the named event `name`, requesting the processes listening for it
+/// with `named_event` to shut down.
+pub fn signal_named_event(name: &str) -> io::Result<()> {
+    let handle = unsafe { OpenEventW(EVENT_MODIFY_STATE, 0, wide(name).as_ptr()) };
+    if handle.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    let handle = Handle(handle);
+    if unsafe { SetEvent(handle.0) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{named_event, signal_named_event};
+    use crate::{CtrlcAsError, KeyboardInterrupt};
+    use futures::future;
+    use tokio::runtime::current_thread::Runtime;
+
+    #[test]
+    fn test_named_event() {
+        let name = format!("Local\\tokio-ctrlc-error-test-{}", std::process::id());
+        let source = named_event(&name).unwrap();
+        signal_named_event(&name).unwrap();
+        let future = CtrlcAsError::with_signal_source(future::empty::<(), _>(), source);
+        let err: failure::Error = Runtime::new().unwrap().block_on(future).unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+    }
+}
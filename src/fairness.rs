@@ -0,0 +1,123 @@
+//! Yielding to the executor, so that interrupts are honored in bounded time.
+//!
+//! A wrapper can only check for ctrl+c when it is polled. A combinator like
+//! `for_each` over a stream that is always ready never returns to the
+//! executor, so the wrapper around it isn't polled and the interrupt is
+//! delayed until the stream runs dry. The adapters here make such a stream
+//! yield periodically.
+
+use futures::{prelude::*, task};
+use std::time::{Duration, Instant};
+
+/// Stream returned by `yield_every` and `yield_after`.
+pub struct Yielding<S> {
+    stream: S,
+    items: Option<usize>,
+    time: Option<Duration>,
+    /// The number of items yielded since the last yield to the executor.
+    count: usize,
+    /// When the current burst of items started.
+    started: Option<Instant>,
+}
+
+impl<S> Yielding<S> {
+    fn new(stream: S, items: Option<usize>, time: Option<Duration>) -> Self {
+        Yielding {
+            stream,
+            items,
+            time,
+            count: 0,
+            started: None,
+        }
+    }
+
+    fn exhausted(&self) -> bool {
+        let items = self.items.is_some_and(|items| self.count >= items);
+        let time = match (self.time, self.started) {
+            (Some(time), Some(started)) => started.elapsed() >= time,
+            _ => false,
+        };
+        items || time
+    }
+
+    fn reset(&mut self) {
+        self.count = 0;
+        self.started = None;
+    }
+}
+
+impl<S: Stream> Stream for Yielding<S> {
+    type Error = S::Error;
+    type Item = S::Item;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if self.exhausted() {
+            self.reset();
+            task::current().notify();
+            return Ok(Async::NotReady);
+        }
+        let res = self.stream.poll();
+        match res {
+            Ok(Async::Ready(Some(_))) => {
+                self.count += 1;
+                self.started.get_or_insert_with(Instant::now);
+            }
+            Ok(Async::NotReady) => self.reset(),
+            _ => {}
+        }
+        res
+    }
+}
+
+pub trait YieldExt: Stream + Sized {
+    /// Return to the executor after every `items` consecutive items.
+    ///
+    /// # Panics
+    /// Panics if `items` is zero.
+    fn yield_every(self, items: usize) -> Yielding<Self> {
+        assert!(items > 0, "cannot yield after zero items");
+        Yielding::new(self, Some(items), None)
+    }
+
+    /// Return to the executor after yielding items for `time` without a break.
+    fn yield_after(self, time: Duration) -> Yielding<Self> {
+        Yielding::new(self, None, Some(time))
+    }
+}
+
+impl<S: Stream> YieldExt for S {}
+
+#[cfg(test)]
+mod tests {
+    use super::YieldExt;
+    use futures::{future, prelude::*, stream};
+
+    #[test]
+    fn test_yield_every() {
+        let mut stream = stream::iter_ok::<_, ()>(0..10).yield_every(3);
+        let polls = future::lazy(move || {
+            let polls: Vec<_> = (0..5).map(|_| stream.poll().unwrap()).collect();
+            Ok::<_, ()>(polls)
+        });
+        let polls = polls.wait().unwrap();
+        assert_eq!(
+            polls,
+            vec![
+                Async::Ready(Some(0)),
+                Async::Ready(Some(1)),
+                Async::Ready(Some(2)),
+                Async::NotReady,
+                Async::Ready(Some(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_all_items() {
+        let stream = stream::iter_ok::<_, ()>(0..10).yield_every(3);
+        assert_eq!(
+            stream.collect().wait().unwrap(),
+            (0..10).collect::<Vec<_>>()
+        );
+    }
+}
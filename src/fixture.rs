@@ -0,0 +1,79 @@
+//! Fixtures for end-to-end tests of the interrupt handling.
+//!
+//! Real interrupts are delivered to the whole process, so end-to-end tests
+//! should run the code under test in a child process that interrupts itself,
+//! like the `tokio-ctrlc-fixture` binary of this crate does. Start the child
+//! with `command`: on Windows, a console ctrl+c event reaches every process
+//! attached to the console, so the child needs a console of its own not to
+//! interrupt the test runner as well.
+
+use futures::prelude::*;
+use std::{
+    ffi::OsStr,
+    io,
+    process::Command,
+    time::{Duration, Instant},
+};
+use tokio_timer::Delay;
+
+/// Send SIGINT to the current process, as if the user pressed ctrl+c.
+///
+/// Unless a handler is installed, this terminates the process.
+#[cfg(unix)]
+pub fn raise_ctrlc() -> io::Result<()> {
+    if unsafe { libc::raise(libc::SIGINT) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Send a ctrl+c event to the console of the current process, as if the user
+/// pressed ctrl+c.
+///
+/// Every process attached to the console receives it, so only call it in a
+/// child started with `command`. Unless a handler is installed, this
+/// terminates the process.
+#[cfg(windows)]
+pub fn raise_ctrlc() -> io::Result<()> {
+    use windows_sys::Win32::System::Console::{
+        GenerateConsoleCtrlEvent, SetConsoleCtrlHandler, CTRL_C_EVENT,
+    };
+
+    // The processes started in a new process group ignore ctrl+c until it's
+    // enabled again
+    if unsafe { SetConsoleCtrlHandler(None, 0) } == 0
+        || unsafe { GenerateConsoleCtrlEvent(CTRL_C_EVENT, 0) } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// A command running `program` as a child that can interrupt itself with
+/// `raise_ctrlc`, without interrupting the test runner.
+///
+/// On Windows, the child is started in a new console and process group. A
+/// signal raised on Unix only reaches the child anyway.
+pub fn command<S: AsRef<OsStr>>(program: S) -> Command {
+    #[cfg_attr(unix, allow(unused_mut))]
+    let mut command = Command::new(program);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        use windows_sys::Win32::System::Threading::{CREATE_NEW_CONSOLE, CREATE_NEW_PROCESS_GROUP};
+
+        command.creation_flags(CREATE_NEW_CONSOLE | CREATE_NEW_PROCESS_GROUP);
+    }
+    command
+}
+
+/// Send SIGINT to the current process after `delay`.
+///
+/// The wrappers under test install their handlers when first polled, so make
+/// sure they are polled before the delay elapses, e.g. by joining them with
+/// this future.
+pub fn ctrlc_after(delay: Duration) -> impl Future<Item = (), Error = io::Error> {
+    Delay::new(Instant::now() + delay)
+        .map_err(io::Error::other)
+        .and_then(|()| raise_ctrlc())
+}
@@ -0,0 +1,128 @@
+//! Ctrl+C key events as interrupts, for terminals in raw mode.
+//!
+//! In raw mode the terminal doesn't turn ctrl+c into SIGINT, but passes it to
+//! the application as a key event. The adapters here turn such events back
+//! into interrupts, to be used with `CtrlcAsError::with_signal_source`.
+
+use futures::{prelude::*, try_ready};
+use std::io;
+
+fn poll_keys<S, P>(events: &mut S, is_ctrlc: P) -> Poll<Option<()>, io::Error>
+where
+    S: Stream<Error = io::Error>,
+    P: Fn(&S::Item) -> bool,
+{
+    loop {
+        match try_ready!(events.poll()) {
+            Some(ref event) if is_ctrlc(event) => return Ok(Async::Ready(Some(()))),
+            Some(_) => {}
+            None => return Ok(Async::Ready(None)),
+        }
+    }
+}
+
+/// Stream returned by `crossterm_interrupts`.
+#[cfg(feature = "crossterm")]
+pub struct CrosstermInterrupts<S> {
+    events: S,
+}
+
+/// Convert a stream of crossterm events into a stream yielding an item for
+/// every Ctrl+C key press.
+#[cfg(feature = "crossterm")]
+pub fn crossterm_interrupts<S>(events: S) -> CrosstermInterrupts<S>
+where
+    S: Stream<Item = crossterm::event::Event, Error = io::Error>,
+{
+    CrosstermInterrupts { events }
+}
+
+#[cfg(feature = "crossterm")]
+fn is_crossterm_ctrlc(event: &crossterm::event::Event) -> bool {
+    use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
+
+    match event {
+        Event::Key(key) => {
+            key.code == KeyCode::Char('c')
+                && key.modifiers.contains(KeyModifiers::CONTROL)
+                && key.kind == KeyEventKind::Press
+        }
+        _ => false,
+    }
+}
+
+#[cfg(feature = "crossterm")]
+impl<S> Stream for CrosstermInterrupts<S>
+where
+    S: Stream<Item = crossterm::event::Event, Error = io::Error>,
+{
+    type Error = io::Error;
+    type Item = ();
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        poll_keys(&mut self.events, is_crossterm_ctrlc)
+    }
+}
+
+/// Stream returned by `termion_interrupts`.
+#[cfg(feature = "termion")]
+pub struct TermionInterrupts<S> {
+    keys: S,
+}
+
+/// Convert a stream of termion keys into a stream yielding an item for every
+/// Ctrl+C key press.
+#[cfg(feature = "termion")]
+pub fn termion_interrupts<S>(keys: S) -> TermionInterrupts<S>
+where
+    S: Stream<Item = termion::event::Key, Error = io::Error>,
+{
+    TermionInterrupts { keys }
+}
+
+#[cfg(feature = "termion")]
+impl<S> Stream for TermionInterrupts<S>
+where
+    S: Stream<Item = termion::event::Key, Error = io::Error>,
+{
+    type Error = io::Error;
+    type Item = ();
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        poll_keys(&mut self.keys, |key| *key == termion::event::Key::Ctrl('c'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{prelude::*, stream};
+
+    #[cfg(feature = "crossterm")]
+    #[test]
+    fn test_crossterm() {
+        use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+        let events = vec![
+            Event::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::NONE)),
+            Event::FocusLost,
+            Event::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+        ];
+        let interrupts = super::crossterm_interrupts(stream::iter_ok(events));
+        assert_eq!(interrupts.collect().wait().unwrap(), vec![()]);
+    }
+
+    #[cfg(feature = "termion")]
+    #[test]
+    fn test_termion() {
+        use termion::event::Key;
+
+        let keys = vec![
+            Key::Char('c'),
+            Key::Ctrl('c'),
+            Key::Ctrl('d'),
+            Key::Ctrl('c'),
+        ];
+        let interrupts = super::termion_interrupts(stream::iter_ok(keys));
+        assert_eq!(interrupts.collect().wait().unwrap(), vec![(), ()]);
+    }
+}
@@ -0,0 +1,56 @@
+//! The futures 0.1 API, under a path that stays stable across the port to
+//! `std::future::Future`.
+//!
+//! The root of the crate is still built on futures 0.1, while the `std_future`
+//! module is the API the crate moves to. Importing the futures 0.1 wrappers
+//! from here keeps them compiling once the root switches, and `into_std` lets
+//! them be awaited by code that has already migrated, one part of the program
+//! at a time.
+//!
+//! The delays of the futures 0.1 wrappers, e.g. for
+//! `HandlerErrorPolicy::Reregister` or `uninterruptible_for`, still use the
+//! timer of the tokio 0.1 runtime. Without one, e.g. on a futures 0.3
+//! executor, they fail with an `IoError` for which `is_timer` returns true
+//! once they start a delay. Plain ctrl+c handling doesn't need a timer.
+//!
+//! # Examples
+//! ```
+//!     use tokio_ctrlc_error::legacy::{into_std, AsyncCtrlc};
+//!
+//!     // Still returned by a futures 0.1 library
+//!     let task = futures::future::ok::<_, failure::Error>(()).ctrlc_as_error();
+//!     futures03::executor::block_on(async { into_std(task).await }).unwrap();
+//! ```
+
+use ::futures03::compat::Compat01As03;
+
+pub use crate::{
+    AsyncCtrlc, CtrlcAsError, HandlerErrorPolicy, InterruptOptions, IoError, KeyboardInterrupt,
+};
+
+/// Adapt a futures 0.1 wrapper into a `std::future::Future`.
+///
+/// The signal handling is driven as with the `std_future` wrappers, on any
+/// executor, except for the delays needing a tokio 0.1 timer, see the module
+/// documentation.
+pub fn into_std<F: futures::Future>(wrapper: CtrlcAsError<F>) -> Compat01As03<CtrlcAsError<F>> {
+    Compat01As03::new(wrapper)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{into_std, AsyncCtrlc, CtrlcAsError, KeyboardInterrupt};
+    use crate::listener::Listener;
+    use futures::future;
+
+    #[test]
+    fn test_into_std() {
+        let task = future::ok::<_, failure::Error>(1).ctrlc_as_error();
+        assert_eq!(futures03::executor::block_on(into_std(task)).unwrap(), 1);
+
+        let ctrlc = Listener::interrupted();
+        let task = CtrlcAsError::with_listener(ctrlc, future::empty::<(), failure::Error>());
+        let err = futures03::executor::block_on(into_std(task)).unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+    }
+}
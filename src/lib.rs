@@ -1,4 +1,4 @@
-//! Easy ctrl+c handling with `failure` and `futures`.
+//! Easy ctrl+c handling with `futures`.
 //!
 //! In many cases, a ctrl+c event from the user is hardly different from
 //! a fatal application error. This crate, inspired by Python's `InterruptedException`
@@ -45,25 +45,241 @@
 //!
 //! Here, the interrupt will be handled only during the first sleep.
 //! During the second sleep, the default handling of the signal will take place.
+//! To react to the same interrupt anywhere in the program, share a
+//! `CtrlcToken` instead.
+//!
+//! # Multiple runtimes
+//! The wrappers can be used from several runtimes in the same process, e.g. a
+//! main runtime and a `current_thread` runtime bridging blocking code. The
+//! handler is registered once per process, by a listener running on a thread
+//! of its own, and a ctrl+c is delivered to **every wrapper that has been
+//! polled at least once** before the signal, whichever runtime it runs on. A
+//! wrapper subscribes on its first poll, so one created after the signal, or
+//! never polled, doesn't see it.
+//!
+//! The wrappers don't depend on the reactor of their runtime, so dropping a
+//! runtime only affects its own wrappers. A wrapper given a reactor with
+//! `InterruptOptions::reactor` registers with it on its own instead.
+//!
+//! # Features
+//! * `anyhow` — adds `InterruptKind::of_anyhow`, `find_interrupt_anyhow` and
+//!   `report_anyhow`, for applications using `anyhow::Error`.
+//! * `bench` — adds the `counters` module, counting the work done by the
+//!   interrupt handling, to measure its overhead.
+//! * `clap` — adds `ShutdownArgs`, command line flags for `InterruptOptions`.
+//! * `crossterm`, `termion` — adapters turning Ctrl+C key events of a raw mode
+//!   terminal into interrupts, see the `keys` module.
+//! * `failure` (default) — adds `InterruptKind::of_failure`, `find_interrupt`
+//!   and `report`, for applications using `failure::Error`.
+//! * `fixtures` — adds the `fixture` module, helpers for end-to-end tests
+//!   of interrupt handling, and the `tokio-ctrlc-fixture` binary using them.
+//! * `futures03` — adds the `std_future` module, the wrappers for
+//!   `std::future::Future`, usable with `async fn` and `.await`.
+//! * `legacy-0_1` — adds the `legacy` module, the futures 0.1 API under a
+//!   path that stays stable across the port to `std::future::Future`.
+//! * `miette` — implements `miette::Diagnostic` for the error types, so that
+//!   interrupts are rendered like the rest of the application's diagnostics.
+//! * `snafu` — adds `ctrlc_context`, which builds the errors from snafu context
+//!   selectors instead of requiring `From` conversions.
+//! * `terminal` — adds `prompt`, an interruptible read of a line from stdin,
+//!   `stdin_lines`, an interruptible stream of the lines of stdin, and
+//!   `end_of_input_as_error`, which turns the end of stdin into an error.
+//! * `testing` — adds the `testing` module, with `MockSignals` to trigger
+//!   interrupts from the unit tests, without sending signals to the process.
+//! * `tokio` — adds `run_interruptible`, which blocks on a future with a tokio
+//!   runtime and returns the exit code of the program.
+//! * `tracing` — emits events with the target `tokio_ctrlc_error::shutdown`
+//!   along the interrupt lifecycle: when an interrupt is received, swallowed
+//!   by the escalation policy or deferred, when a wrapper returns the error,
+//!   with the time elapsed since the interrupt, and when a cleanup runs. The
+//!   installation and the failures of the handler are reported with the
+//!   target `tokio_ctrlc_error`.
 
-use failure::Fail;
-use futures::{prelude::*, FlattenStream};
-use tokio_signal::{IoFuture, IoStream};
+use crate::listener::Listener;
+use futures::prelude::*;
+use std::{io, time::Duration};
 
-#[derive(Debug, Fail)]
-#[fail(display = "keyboard interrupt")]
-pub struct KeyboardInterrupt;
+mod async_io;
+mod batch;
+mod cancel;
+mod check;
+mod checkpoint;
+mod cleanup;
+#[cfg(feature = "clap")]
+mod cli;
+#[cfg(windows)]
+mod console;
+#[cfg(feature = "snafu")]
+mod context;
+#[cfg(unix)]
+mod control;
+#[cfg(feature = "bench")]
+pub mod counters;
+mod deadlines;
+mod defer;
+mod detach;
+#[cfg(unix)]
+mod disposition;
+mod error;
+#[cfg(windows)]
+mod event;
+mod fairness;
+#[cfg(all(feature = "fixtures", any(unix, windows)))]
+pub mod fixture;
+#[cfg(any(feature = "crossterm", feature = "termion"))]
+pub mod keys;
+#[cfg(feature = "legacy-0_1")]
+pub mod legacy;
+mod listener;
+mod options;
+mod outcome;
+mod panic;
+mod pipeline;
+mod policy;
+pub mod prelude;
+mod report;
+mod retry;
+mod schedule;
+mod shared;
+#[cfg(unix)]
+mod signal;
+mod sink;
+mod status;
+#[cfg(feature = "futures03")]
+pub mod std_future;
+mod stream;
+mod suspend;
+mod tasks;
+#[cfg(feature = "terminal")]
+mod terminal;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod timeout;
+mod token;
+mod trigger;
 
-#[derive(Debug, Fail)]
-#[fail(display = "I/O error handling ctrl+c: {}", _0)]
-pub struct IoError(std::io::Error);
+pub use crate::batch::{interrupt_all, select_ok, InterruptAll};
+pub use crate::cancel::{CancelFlag, CancelOnCtrlc};
+pub use crate::check::InterruptCheck;
+pub use crate::checkpoint::{CtrlcCheckpoint, FileSink, StateSink};
+pub use crate::cleanup::CtrlcWithCleanup;
+#[cfg(feature = "clap")]
+pub use crate::cli::ShutdownArgs;
+#[cfg(feature = "snafu")]
+pub use crate::context::{AsyncCtrlcContext, CtrlcContext};
+#[cfg(unix)]
+pub use crate::control::{control_socket, request_shutdown, ControlSocket};
+pub use crate::defer::uninterruptible_for;
+pub use crate::detach::{detach_until_shutdown, Detached};
+#[cfg(unix)]
+pub use crate::disposition::{
+    diagnose_ctrlc_handler, exit_on_unhandled_ctrlc, uninstall, HandlerConflict,
+};
+#[cfg(windows)]
+pub use crate::event::{named_event, signal_named_event, NamedEvent};
+#[cfg(feature = "tokio")]
+pub use crate::report::run_interruptible;
+pub use crate::{
+    async_io::{AsyncCtrlcIo, CtrlcRead, CtrlcWrite},
+    error::{
+        find_interrupt_std, DeadlineExceeded, InterruptKind, InterruptLike, IoError,
+        KeyboardInterrupt, StageInterrupted, TriggerInterrupt,
+    },
+    fairness::{YieldExt, Yielding},
+    options::{EscalationPolicy, HandlerErrorPolicy, InterruptOptions},
+    outcome::{CtrlcAsResult, ItemOrInterrupt},
+    panic::{AsyncCtrlcPanic, CtrlcAsPanic},
+    pipeline::{stage, Pipeline},
+    policy::{CtrlcWithPolicy, InterruptDecision, InterruptPolicy},
+    report::{exit_as_signaled, report_std},
+    retry::{ctrlc_retry, AttemptFailure, CtrlcRetry, RetryErrors, RetryPolicy},
+    schedule::{CtrlcDelayQueue, QueueKey, Scheduled},
+    sink::{AsyncCtrlcSink, CtrlcSink},
+    status::{status, Status},
+    stream::{merge_all, zip, AsyncCtrlcStream, CtrlcAsErrorStream, CtrlcDrain, MergeAll},
+    suspend::{ctrlc_suspended, suspend_ctrlc, CtrlcSuspended, SuspendGuard},
+    tasks::{running_tasks, track_task, DrainProgress, ProgressReporter, RunningTask, TaskGuard},
+    timeout::CtrlcOrTimeout,
+    token::{CtrlcToken, Interrupted, WithToken},
+    trigger::{deadline_at, AsyncTrigger, InterruptOn},
+};
+#[cfg(windows)]
+pub use crate::{
+    console::{AsyncConsole, ConsoleAsError},
+    error::{ConsoleEvent, ConsoleInterrupt},
+};
+#[cfg(feature = "failure")]
+pub use crate::{error::find_interrupt, report::report};
+#[cfg(feature = "anyhow")]
+pub use crate::{error::find_interrupt_anyhow, report::report_anyhow};
+#[cfg(feature = "terminal")]
+pub use crate::{
+    error::EndOfInput,
+    terminal::{prompt, stdin_lines, AsyncEndOfInput, EndOfInputAsError, Prompt, StdinLines},
+};
+#[cfg(unix)]
+pub use crate::{
+    error::SignalInterrupt,
+    signal::{AsyncSignal, SignalAsError},
+};
 
+/// Future returned by `ctrlc_as_error`.
+///
+/// How ctrl+c is detected is an implementation detail: the type only exposes
+/// the wrapped future, and is `Send` whenever the wrapped future is.
 pub struct CtrlcAsError<F> {
-    // we will switch to `struct CtrlC` in tokio 0.3
-    ctrlc: FlattenStream<IoFuture<IoStream<()>>>,
+    ctrlc: Listener,
+    hooks: Vec<Box<dyn FnOnce() + Send>>,
     future: F,
 }
 
+impl<F> CtrlcAsError<F> {
+    /// Wrap `future`, treating every item of `source` as ctrl+c, instead of
+    /// listening for the signal.
+    ///
+    /// This allows to interrupt the future by other means, e.g. Ctrl+C key
+    /// presses in raw terminal mode, see the `keys` module, or by the tests,
+    /// see `testing::MockSignals`.
+    pub fn with_signal_source<S>(future: F, source: S) -> Self
+    where
+        S: Stream<Item = (), Error = io::Error> + Send + 'static,
+    {
+        Self::with_listener(Listener::from_stream(Box::new(source)), future)
+    }
+
+    pub(crate) fn with_listener(ctrlc: Listener, future: F) -> Self {
+        CtrlcAsError {
+            ctrlc,
+            hooks: Vec::new(),
+            future,
+        }
+    }
+
+    /// Run `hook` as soon as ctrl+c is received, before the error is returned.
+    ///
+    /// The hooks run in the order they were added, even if the error is
+    /// swallowed further up the chain, e.g. to print a message or to leave
+    /// raw terminal mode right away.
+    ///
+    /// # Examples
+    /// ```
+    ///     use tokio_ctrlc_error::AsyncCtrlc;
+    ///
+    ///     let fut = futures::future::ok::<_, failure::Error>(())
+    ///         .ctrlc_as_error()
+    ///         .on_interrupt(|| eprintln!("shutting down..."));
+    ///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+    ///     rt.block_on(fut).unwrap();
+    /// ```
+    pub fn on_interrupt<H>(mut self, hook: H) -> Self
+    where
+        H: FnOnce() + Send + 'static,
+    {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+}
+
 impl<F: Future> Future for CtrlcAsError<F>
 where
     F::Error: From<KeyboardInterrupt> + From<IoError>,
@@ -72,11 +288,13 @@ where
     type Item = F::Item;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        let ctrlc_fut = self.ctrlc.poll().map_err(IoError)?;
-        if ctrlc_fut.is_ready() {
-            Err(KeyboardInterrupt.into())
+        if self.ctrlc.poll()?.is_ready() {
+            for hook in self.hooks.drain(..) {
+                hook();
+            }
+            Err(self.ctrlc.interrupt().into())
         } else {
-            self.future.poll().map_err(Into::into)
+            self.future.poll()
         }
     }
 }
@@ -84,6 +302,77 @@ where
 pub trait AsyncCtrlc<F: Future> {
     /// Intercept ctrl+c during execution and return an error in such case.
     fn ctrlc_as_error(self) -> CtrlcAsError<F>;
+
+    /// Like `ctrlc_as_error`, but configured with `options`, or with an
+    /// `EscalationPolicy`:
+    /// ```
+    ///     use std::time::Duration;
+    ///     use tokio_ctrlc_error::{AsyncCtrlc, EscalationPolicy};
+    ///
+    ///     let fut = futures::future::ok::<_, failure::Error>(())
+    ///         .ctrlc_as_error_with(EscalationPolicy::ForceOnSecond(Duration::from_secs(5)));
+    ///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+    ///     rt.block_on(fut).unwrap();
+    /// ```
+    fn ctrlc_as_error_with<O: Into<InterruptOptions>>(self, options: O) -> CtrlcAsError<F>;
+
+    /// Like `ctrlc_as_error`, but resolve to `ItemOrInterrupt::Interrupted` on
+    /// ctrl+c instead of failing, for tasks where stopping early is a normal
+    /// outcome.
+    /// ```
+    ///     use tokio_ctrlc_error::{AsyncCtrlc, ItemOrInterrupt};
+    ///
+    ///     let fut = futures::future::ok::<_, failure::Error>(5).ctrlc_as_result();
+    ///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+    ///     match rt.block_on(fut).unwrap() {
+    ///         ItemOrInterrupt::Done(n) => assert_eq!(n, 5),
+    ///         ItemOrInterrupt::Interrupted => println!("stopped early"),
+    ///     }
+    /// ```
+    fn ctrlc_as_result(self) -> CtrlcAsResult<F>;
+
+    /// Like `ctrlc_as_error`, but also fail with `DeadlineExceeded` if the
+    /// future doesn't complete within `timeout`.
+    ///
+    /// Unlike wrapping in `tokio_timer::Timeout`, both errors are converted
+    /// into `F::Error` directly, so they can be told apart without unwrapping
+    /// the timer error first:
+    /// ```
+    ///     use std::time::Duration;
+    ///     use tokio_ctrlc_error::{AsyncCtrlc, DeadlineExceeded};
+    ///
+    ///     let fut = futures::future::empty::<(), failure::Error>()
+    ///         .ctrlc_or_timeout(Duration::from_millis(10));
+    ///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+    ///     let err = rt.block_on(fut).unwrap_err();
+    ///     assert!(err.downcast_ref::<DeadlineExceeded>().is_some());
+    /// ```
+    fn ctrlc_or_timeout(self, timeout: Duration) -> CtrlcOrTimeout<F>
+    where
+        F::Error: From<DeadlineExceeded>;
+
+    /// Like `ctrlc_as_error`, but let `policy` decide what to do on every
+    /// ctrl+c.
+    fn ctrlc_with_policy<P: InterruptPolicy>(self, policy: P) -> CtrlcWithPolicy<F, P>;
+
+    /// Like `ctrlc_as_error`, but on ctrl+c take a snapshot of the application
+    /// state and save it to `sink` before returning the error.
+    ///
+    /// If saving the snapshot fails, an `IoError` is returned instead.
+    fn ctrlc_checkpoint<S, G, K>(self, snapshot: G, sink: K) -> CtrlcCheckpoint<F, G, K>
+    where
+        G: FnMut() -> S,
+        K: StateSink<S>;
+
+    /// Like `ctrlc_as_error`, but on ctrl+c drop the future, run `cleanup`
+    /// and only then return the error, e.g. to flush buffers.
+    ///
+    /// The cleanup is aborted if it takes longer than `grace`, or on another
+    /// ctrl+c. If it fails, its error is returned instead of the
+    /// `KeyboardInterrupt`.
+    fn ctrlc_with_cleanup<C>(self, cleanup: C, grace: Duration) -> CtrlcWithCleanup<F, C::Future>
+    where
+        C: IntoFuture<Item = (), Error = F::Error>;
 }
 
 impl<F: Future> AsyncCtrlc<F> for F
@@ -91,10 +380,41 @@ where
     F::Error: From<KeyboardInterrupt> + From<IoError>,
 {
     fn ctrlc_as_error(self) -> CtrlcAsError<F> {
-        CtrlcAsError {
-            ctrlc: tokio_signal::ctrl_c().flatten_stream(),
-            future: self,
-        }
+        self.ctrlc_as_error_with(InterruptOptions::default())
+    }
+
+    fn ctrlc_as_error_with<O: Into<InterruptOptions>>(self, options: O) -> CtrlcAsError<F> {
+        CtrlcAsError::with_listener(Listener::with_options(options.into()), self)
+    }
+
+    fn ctrlc_as_result(self) -> CtrlcAsResult<F> {
+        CtrlcAsResult::new(self)
+    }
+
+    fn ctrlc_or_timeout(self, timeout: Duration) -> CtrlcOrTimeout<F>
+    where
+        F::Error: From<DeadlineExceeded>,
+    {
+        CtrlcOrTimeout::new(self, timeout)
+    }
+
+    fn ctrlc_with_policy<P: InterruptPolicy>(self, policy: P) -> CtrlcWithPolicy<F, P> {
+        CtrlcWithPolicy::new(self, policy)
+    }
+
+    fn ctrlc_checkpoint<S, G, K>(self, snapshot: G, sink: K) -> CtrlcCheckpoint<F, G, K>
+    where
+        G: FnMut() -> S,
+        K: StateSink<S>,
+    {
+        CtrlcCheckpoint::new(self, snapshot, sink)
+    }
+
+    fn ctrlc_with_cleanup<C>(self, cleanup: C, grace: Duration) -> CtrlcWithCleanup<F, C::Future>
+    where
+        C: IntoFuture<Item = (), Error = F::Error>,
+    {
+        CtrlcWithCleanup::new(self, cleanup.into_future(), grace)
     }
 }
 
@@ -117,6 +437,72 @@ mod tests {
         rt.block_on(future).unwrap();
     }
 
+    // The backend must not affect the auto traits of the public types
+    #[test]
+    fn test_auto_traits() {
+        fn assert_send<T: Send>() {}
+        type Fut = futures::future::FutureResult<(), failure::Error>;
+
+        assert_send::<super::CtrlcAsError<Fut>>();
+        assert_send::<super::InterruptAll<Fut>>();
+    }
+
+    #[test]
+    fn test_signal_source() {
+        use super::{CtrlcAsError, KeyboardInterrupt};
+        use tokio::runtime::current_thread::Runtime;
+
+        let source = futures::stream::once(Ok(()));
+        let future = CtrlcAsError::with_signal_source(futures::future::empty::<(), _>(), source);
+        let mut rt = Runtime::new().unwrap();
+        let err: failure::Error = rt.block_on(future).unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+
+        // The source ending doesn't interrupt the future
+        let source = futures::stream::empty();
+        let future = CtrlcAsError::with_signal_source(futures::future::ok(()), source);
+        let res: Result<_, failure::Error> = rt.block_on(future);
+        res.unwrap();
+    }
+
+    #[test]
+    fn test_on_interrupt() {
+        use super::CtrlcAsError;
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let (first, second) = (calls.clone(), calls.clone());
+        let source = futures::stream::once(Ok(()));
+        let future = CtrlcAsError::with_signal_source(futures::future::empty::<(), _>(), source)
+            .on_interrupt(move || assert_eq!(first.fetch_add(1, Ordering::SeqCst), 0))
+            .on_interrupt(move || assert_eq!(second.fetch_add(1, Ordering::SeqCst), 1));
+        let res: Result<_, failure::Error> = future.wait();
+        assert!(res.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    // The wrappers work with errors not built on `failure`
+    #[test]
+    fn test_std_errors() {
+        use super::{CtrlcAsError, KeyboardInterrupt};
+        use std::error::Error;
+        use tokio::runtime::current_thread::Runtime;
+
+        let mut rt = Runtime::new().unwrap();
+        let source = futures::stream::once(Ok(()));
+        let future = CtrlcAsError::with_signal_source(futures::future::empty::<(), _>(), source);
+        let err: Box<dyn Error + Send + Sync> = rt.block_on(future).unwrap_err();
+        assert!(err.is::<KeyboardInterrupt>());
+
+        let source = futures::stream::once(Ok(()));
+        let future = CtrlcAsError::with_signal_source(futures::future::empty::<(), _>(), source);
+        let err: anyhow::Error = rt.block_on(future).unwrap_err();
+        assert!(err.is::<KeyboardInterrupt>());
+    }
+
     // Test if it compiles when used with the single-threaded runtime
     #[test]
     fn test_non_send_future() {
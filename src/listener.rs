@@ -0,0 +1,683 @@
+use crate::{
+    defer, error::Received, suspend, EscalationPolicy, HandlerErrorPolicy, InterruptOptions,
+    IoError, KeyboardInterrupt,
+};
+use futures::prelude::*;
+use std::{
+    cmp, io,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+use tokio_signal::{IoFuture, IoStream};
+use tokio_timer::Delay;
+
+/// The number of listeners currently alive.
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+/// Whether the ctrl+c handler has been installed.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+enum State {
+    // we will switch to `struct CtrlC` in tokio 0.3
+    Installing(IoFuture<IoStream<()>>),
+    Listening(IoStream<()>),
+    /// Waiting to register the handler again after an error.
+    Backoff(Delay),
+    Disabled,
+}
+
+/// The ctrl+c stream shared by all the wrappers in this crate.
+///
+/// The state shared between listeners is kept in atomics, so that wrappers
+/// running on different runtimes and threads don't contend with each other.
+pub(crate) struct Listener {
+    state: State,
+    options: InterruptOptions,
+    /// Whether the events come from the signal, so that it can be registered
+    /// again.
+    signal: bool,
+    /// The delay before the next attempt to register the handler.
+    backoff: Option<Duration>,
+    /// The end of the `uninterruptible_for` window that deferred an interrupt.
+    deferred: Option<Delay>,
+    /// The number of interrupts seen by this listener.
+    seen: usize,
+    /// When the first interrupt was received.
+    first: Option<(Instant, SystemTime)>,
+    /// The details shared with the errors returned, see `interrupt`.
+    received: Option<Arc<Received>>,
+    /// When the interrupt swallowed by `EscalationPolicy::ForceOnSecond` was
+    /// received.
+    swallowed: Option<Instant>,
+    /// The polls skipped in a row, see `InterruptOptions::poll_budget`.
+    skipped: u32,
+    /// The interrupts received by the shared listener when the signal stream
+    /// was last polled.
+    last_received: usize,
+    /// The name of the trigger listened for, if created with `for_trigger`.
+    trigger: Option<&'static str>,
+    /// The signal listened for, SIGINT unless created with `for_signal`.
+    #[cfg(unix)]
+    signum: libc::c_int,
+}
+
+impl Listener {
+    pub(crate) fn new() -> Self {
+        Self::with_options(InterruptOptions::default())
+    }
+
+    pub(crate) fn with_options(options: InterruptOptions) -> Self {
+        Self::from_future(ctrl_c(&options), options)
+    }
+
+    /// Listen for events from `source` instead of ctrl+c.
+    pub(crate) fn from_stream(source: IoStream<()>) -> Self {
+        ACTIVE.fetch_add(1, Ordering::Relaxed);
+        Listener {
+            state: State::Listening(source),
+            options: InterruptOptions::default(),
+            signal: false,
+            backoff: None,
+            deferred: None,
+            seen: 0,
+            first: None,
+            received: None,
+            swallowed: None,
+            skipped: 0,
+            last_received: 0,
+            trigger: None,
+            #[cfg(unix)]
+            signum: libc::SIGINT,
+        }
+    }
+
+    /// Listen for the trigger `name` firing instead of ctrl+c.
+    pub(crate) fn for_trigger(name: &'static str, source: IoStream<()>) -> Self {
+        let mut listener = Self::from_stream(source);
+        listener.trigger = Some(name);
+        listener
+    }
+
+    #[cfg(test)]
+    pub(crate) fn options(&self) -> &InterruptOptions {
+        &self.options
+    }
+
+    /// A listener seeing one interrupt right away, for the tests.
+    #[cfg(test)]
+    pub(crate) fn interrupted() -> Self {
+        Self::from_stream(Box::new(futures::stream::once(Ok(()))))
+    }
+
+    /// Listen for the Unix signal `signum` instead of ctrl+c.
+    #[cfg(unix)]
+    pub(crate) fn for_signal(signum: libc::c_int, options: InterruptOptions) -> Self {
+        let mut listener = Self::from_future(unix_signal(signum, &options), options);
+        listener.signum = signum;
+        listener
+    }
+
+    /// Whether the listener is for ctrl+c rather than another signal.
+    fn is_ctrlc(&self) -> bool {
+        if self.trigger.is_some() {
+            return false;
+        }
+        #[cfg(unix)]
+        {
+            self.signum == libc::SIGINT
+        }
+        #[cfg(not(unix))]
+        {
+            true
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    fn signal_name(&self) -> &'static str {
+        if let Some(name) = self.trigger {
+            return name;
+        }
+        #[cfg(unix)]
+        {
+            if !self.is_ctrlc() {
+                return crate::error::signal_name(self.signum).unwrap_or("other");
+            }
+        }
+        "ctrl_c"
+    }
+
+    /// Register the signal handler again.
+    fn register(&self) -> IoFuture<IoStream<()>> {
+        #[cfg(unix)]
+        {
+            if !self.is_ctrlc() {
+                return unix_signal(self.signum, &self.options);
+            }
+        }
+        ctrl_c(&self.options)
+    }
+
+    fn from_future(ctrlc: IoFuture<IoStream<()>>, options: InterruptOptions) -> Self {
+        ACTIVE.fetch_add(1, Ordering::Relaxed);
+        Listener {
+            state: State::Installing(ctrlc),
+            options,
+            signal: true,
+            backoff: None,
+            deferred: None,
+            seen: 0,
+            first: None,
+            received: None,
+            swallowed: None,
+            skipped: 0,
+            last_received: 0,
+            trigger: None,
+            #[cfg(unix)]
+            signum: libc::SIGINT,
+        }
+    }
+
+    /// Returns `Ready` once ctrl+c has been pressed.
+    pub(crate) fn poll(&mut self) -> Poll<(), IoError> {
+        #[cfg(feature = "bench")]
+        crate::counters::poll();
+        let mut rearmed = false;
+        let ctrlc = self.is_ctrlc();
+        if self.deferred.is_none() && self.skip_poll() {
+            return Ok(Async::NotReady);
+        }
+        loop {
+            if let Some(ref mut delay) = self.deferred {
+                // The window may have been extended in the meantime
+                if let Some(until) = defer::deferred_until() {
+                    if until > delay.deadline() {
+                        delay.reset(until);
+                    }
+                }
+                if delay.poll().map_err(IoError::timer)?.is_not_ready() {
+                    return Ok(Async::NotReady);
+                }
+                self.deferred = None;
+                return Ok(Async::Ready(()));
+            }
+            match self.state {
+                State::Installing(ref mut future) => match poll_install(future, ctrlc) {
+                    Ok(Async::Ready(stream)) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::debug!(
+                            target: "tokio_ctrlc_error",
+                            signal = self.signal_name(),
+                            "handler installed"
+                        );
+                        self.backoff = None;
+                        self.state = State::Listening(stream);
+                    }
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Err(e) => self.handle_error(e, self.options.install_error)?,
+                },
+                State::Listening(ref mut stream) => {
+                    let res = match stream.poll() {
+                        Ok(res) => res,
+                        Err(e) => {
+                            self.handle_error(e, self.options.stream_error)?;
+                            continue;
+                        }
+                    };
+                    #[cfg(feature = "bench")]
+                    {
+                        if res.is_ready() {
+                            crate::counters::event();
+                        }
+                    }
+                    return match res {
+                        #[cfg(unix)]
+                        Async::Ready(Some(()))
+                            if self.signal
+                                && ctrlc
+                                && self.options.ignore_own_signals
+                                && crate::disposition::sent_by_self() =>
+                        {
+                            continue
+                        }
+                        // Left to the child processes
+                        Async::Ready(Some(())) if self.signal && ctrlc && suspend::suspended() => {
+                            #[cfg(feature = "tracing")]
+                            tracing::debug!(
+                                target: "tokio_ctrlc_error::shutdown",
+                                "interrupt received while suspended, ignoring it"
+                            );
+                            continue;
+                        }
+                        Async::Ready(Some(())) => {
+                            if ctrlc {
+                                self.count();
+                            }
+                            #[cfg(feature = "tracing")]
+                            tracing::info!(
+                                target: "tokio_ctrlc_error::shutdown",
+                                signal = self.signal_name(),
+                                count = self.seen,
+                                active_wrappers = active(),
+                                "interrupt received"
+                            );
+                            if self.swallow() {
+                                #[cfg(feature = "tracing")]
+                                tracing::info!(
+                                    target: "tokio_ctrlc_error::shutdown",
+                                    "escalation policy swallowed the interrupt, waiting for another one"
+                                );
+                                continue;
+                            }
+                            if let Some(until) = defer::deferred_until() {
+                                #[cfg(feature = "tracing")]
+                                tracing::info!(
+                                    target: "tokio_ctrlc_error::shutdown",
+                                    remaining = ?until.saturating_duration_since(Instant::now()),
+                                    "interrupt deferred until the uninterruptible section ends"
+                                );
+                                self.deferred = Some(Delay::new(until));
+                                continue;
+                            }
+                            Ok(Async::Ready(()))
+                        }
+                        // The signal registration is armed again, to see the
+                        // next interrupts. Only once per call, in case the new
+                        // stream ends right away too.
+                        Async::Ready(None) if self.signal && !rearmed => {
+                            rearmed = true;
+                            self.state = State::Installing(self.register());
+                            continue;
+                        }
+                        // The source won't deliver any more interrupts
+                        Async::Ready(None) => {
+                            self.state = State::Disabled;
+                            Ok(Async::NotReady)
+                        }
+                        Async::NotReady => Ok(Async::NotReady),
+                    };
+                }
+                State::Backoff(ref mut delay) => {
+                    if delay.poll().map_err(IoError::timer)?.is_not_ready() {
+                        return Ok(Async::NotReady);
+                    }
+                    self.state = State::Installing(self.register());
+                }
+                State::Disabled => return Ok(Async::NotReady),
+            }
+        }
+    }
+
+    /// Whether the events come from the listener shared by the process.
+    fn is_shared(&self) -> bool {
+        self.signal && self.is_ctrlc() && self.options.reactor.is_none()
+    }
+
+    /// Whether the signal stream can be left alone for this poll, see
+    /// `InterruptOptions::poll_budget`.
+    fn skip_poll(&mut self) -> bool {
+        let budget = self.options.poll_budget;
+        if budget == 0 || !self.is_shared() {
+            return false;
+        }
+        if let State::Listening(_) = self.state {
+            let received = crate::shared::received();
+            if received == self.last_received && self.skipped < budget {
+                self.skipped += 1;
+                return true;
+            }
+            self.last_received = received;
+        }
+        self.skipped = 0;
+        false
+    }
+
+    /// Count an interrupt, including in the errors already returned.
+    fn count(&mut self) {
+        self.seen += 1;
+        if self.first.is_none() {
+            self.first = Some((Instant::now(), SystemTime::now()));
+        }
+        if let Some(ref received) = self.received {
+            received.count.store(self.seen, Ordering::Release);
+        }
+    }
+
+    /// The error to return once the listener is ready, with the details of
+    /// the interrupts seen so far.
+    ///
+    /// The listener may be polled again, e.g. by the cleanup wrappers, and
+    /// the interrupts it sees are counted in the errors already returned.
+    pub(crate) fn interrupt(&mut self) -> KeyboardInterrupt {
+        if self.received.is_none() {
+            let (first, first_at) = self
+                .first
+                .unwrap_or_else(|| (Instant::now(), SystemTime::now()));
+            // The subscriptions to the shared listener count the interrupts
+            // received after the wrapper is gone too
+            let shared = if self.is_shared() {
+                Some((self.seen, crate::shared::received()))
+            } else {
+                None
+            };
+            let signal = if !self.signal {
+                None
+            } else {
+                #[cfg(unix)]
+                {
+                    crate::error::signal_name(self.signum)
+                }
+                #[cfg(windows)]
+                {
+                    Some("CTRL_C_EVENT")
+                }
+            };
+            #[cfg(feature = "tracing")]
+            tracing::info!(
+                target: "tokio_ctrlc_error::shutdown",
+                count = self.seen,
+                latency = ?first.elapsed(),
+                "wrapper interrupted"
+            );
+            self.received = Some(Arc::new(Received {
+                count: AtomicUsize::new(self.seen),
+                shared,
+                first,
+                first_at,
+                signal,
+            }));
+        }
+        KeyboardInterrupt::received(self.received.clone().unwrap())
+    }
+
+    /// Whether the escalation policy swallows the interrupt just received.
+    fn swallow(&mut self) -> bool {
+        let window = match self.options.escalation {
+            EscalationPolicy::Immediate => return false,
+            EscalationPolicy::ForceOnSecond(window) => window,
+        };
+        let now = Instant::now();
+        if self.swallowed.is_some_and(|first| now - first <= window) {
+            return false;
+        }
+        self.swallowed = Some(now);
+        if let Some(ref flag) = self.options.first_interrupt {
+            flag.cancel();
+        }
+        true
+    }
+
+    /// Apply the policy to a handler error, updating the state if the error
+    /// is not propagated.
+    fn handle_error(&mut self, err: io::Error, policy: HandlerErrorPolicy) -> Result<(), IoError> {
+        match policy {
+            HandlerErrorPolicy::Propagate => Err(err.into()),
+            HandlerErrorPolicy::Reregister { initial, max } if self.signal => {
+                let backoff = self.backoff.unwrap_or(initial);
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    target: "tokio_ctrlc_error",
+                    error = %err,
+                    backoff = ?backoff,
+                    "ctrl+c handler failed, registering it again"
+                );
+                self.backoff = Some(cmp::min(backoff * 2, max));
+                self.state = State::Backoff(Delay::new(Instant::now() + backoff));
+                Ok(())
+            }
+            // A custom source cannot be registered again, so it's disabled
+            HandlerErrorPolicy::Ignore | HandlerErrorPolicy::Reregister { .. } => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    target: "tokio_ctrlc_error",
+                    error = %err,
+                    "ctrl+c handler failed, interrupts will not be intercepted"
+                );
+                self.state = State::Disabled;
+                Ok(())
+            }
+        }
+    }
+}
+
+fn ctrl_c(options: &InterruptOptions) -> IoFuture<IoStream<()>> {
+    #[cfg(feature = "bench")]
+    crate::counters::registration();
+    match options.reactor {
+        Some(ref handle) => tokio_signal::ctrl_c_handle(handle),
+        None => crate::shared::subscribe(),
+    }
+}
+
+#[cfg(unix)]
+fn unix_signal(signum: libc::c_int, options: &InterruptOptions) -> IoFuture<IoStream<()>> {
+    #[cfg(feature = "bench")]
+    crate::counters::registration();
+    use tokio_signal::unix::Signal;
+
+    let handle = options.reactor.clone();
+    // Keeps track of the disposition for `uninstall`, like `poll_install`
+    let signal = futures::future::lazy(move || {
+        crate::disposition::before_install(signum);
+        match handle {
+            Some(ref handle) => Signal::with_handle(signum, handle),
+            None => Signal::new(signum),
+        }
+    });
+    Box::new(signal.map(move |signal| {
+        crate::disposition::installed(signum);
+        Box::new(signal.map(|_| ())) as IoStream<()>
+    }))
+}
+
+/// Poll the registration of the handler, keeping track of the SIGINT
+/// disposition when `ctrlc` is set.
+fn poll_install(future: &mut IoFuture<IoStream<()>>, ctrlc: bool) -> Poll<IoStream<()>, io::Error> {
+    if !ctrlc {
+        return future.poll();
+    }
+    #[cfg(unix)]
+    crate::disposition::before_install(libc::SIGINT);
+    let res = future.poll();
+    if let Ok(Async::Ready(_)) = res {
+        #[cfg(unix)]
+        crate::disposition::installed(libc::SIGINT);
+        set_installed(true);
+    }
+    res
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        ACTIVE.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Returns the number of wrappers currently listening for ctrl+c.
+pub(crate) fn active() -> usize {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+pub(crate) fn installed() -> bool {
+    INSTALLED.load(Ordering::Acquire)
+}
+
+pub(crate) fn set_installed(installed: bool) {
+    INSTALLED.store(installed, Ordering::Release);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Listener, State};
+    use crate::{HandlerErrorPolicy, InterruptOptions};
+    use futures::{future, prelude::*, stream};
+    use std::{io, time::Duration};
+    use tokio::runtime::current_thread::Runtime;
+
+    fn ending() -> tokio_signal::IoFuture<tokio_signal::IoStream<()>> {
+        let stream = stream::once(Ok(()));
+        Box::new(future::ok(Box::new(stream) as tokio_signal::IoStream<()>))
+    }
+
+    fn failing() -> tokio_signal::IoFuture<tokio_signal::IoStream<()>> {
+        Box::new(future::err(io::Error::from(
+            io::ErrorKind::PermissionDenied,
+        )))
+    }
+
+    #[test]
+    fn test_install_error_propagate() {
+        let mut listener = Listener::from_future(failing(), InterruptOptions::new());
+        let err = listener.poll().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    // The future must work without any runtime when given a reactor
+    #[test]
+    fn test_reactor() {
+        use crate::AsyncCtrlc;
+        use tokio_reactor::Reactor;
+
+        let reactor = Reactor::new().unwrap().background().unwrap();
+        let options = InterruptOptions::new().reactor(reactor.handle().clone());
+        let future = future::ok::<_, failure::Error>(()).ctrlc_as_error_with(options);
+        future.wait().unwrap();
+    }
+
+    #[test]
+    fn test_install_error_ignore() {
+        let options = InterruptOptions::new().on_install_error(HandlerErrorPolicy::Ignore);
+        let mut listener = Listener::from_future(failing(), options);
+        assert_eq!(listener.poll().unwrap(), Async::NotReady);
+        assert_eq!(listener.poll().unwrap(), Async::NotReady);
+    }
+
+    #[test]
+    fn test_install_error_reregister() {
+        let policy = HandlerErrorPolicy::Reregister {
+            initial: Duration::from_millis(1),
+            max: Duration::from_millis(4),
+        };
+        let options = InterruptOptions::new().on_install_error(policy);
+        let mut listener = Listener::from_future(failing(), options);
+        let installed = future::poll_fn(move || {
+            assert_eq!(listener.poll()?, Async::NotReady);
+            match listener.state {
+                State::Listening(_) => Ok(Async::Ready(listener.backoff)),
+                _ => Ok(Async::NotReady),
+            }
+        });
+        let backoff = Runtime::new().unwrap().block_on(installed);
+        assert_eq!(backoff.map_err(crate::IoError::into_inner).unwrap(), None);
+    }
+
+    #[test]
+    fn test_stream_error_ignore() {
+        let options = InterruptOptions::new().on_stream_error(HandlerErrorPolicy::Ignore);
+        let source = stream::once(Err(io::Error::from(io::ErrorKind::Other)));
+        let mut listener = Listener::from_stream(Box::new(source));
+        listener.options = options;
+        assert_eq!(listener.poll().unwrap(), Async::NotReady);
+        assert!(matches!(listener.state, State::Disabled));
+
+        // Propagated by default
+        let source = stream::once(Err(io::Error::from(io::ErrorKind::Other)));
+        let mut listener = Listener::from_stream(Box::new(source));
+        assert!(listener.poll().is_err());
+    }
+
+    #[test]
+    fn test_interrupt_details() {
+        let events = stream::iter_ok(vec![(), ()]).chain(future::empty().into_stream());
+        let mut listener = Listener::from_stream(Box::new(events));
+        assert_eq!(listener.poll().unwrap(), Async::Ready(()));
+        let err = listener.interrupt();
+        assert_eq!(err.count(), Some(1));
+        assert!(err.first_received().is_some());
+        assert_eq!(err.signal(), None);
+        // Still counted after the error was returned
+        assert_eq!(listener.poll().unwrap(), Async::Ready(()));
+        assert_eq!(err.count(), Some(2));
+        assert_eq!(listener.interrupt().first_received(), err.first_received());
+        assert_eq!(crate::KeyboardInterrupt::new().count(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_interrupt_signal() {
+        let mut listener = Listener::interrupted();
+        listener.signal = true;
+        listener.signum = libc::SIGTERM;
+        assert_eq!(listener.poll().unwrap(), Async::Ready(()));
+        assert_eq!(listener.interrupt().signal(), Some("SIGTERM"));
+    }
+
+    #[test]
+    fn test_poll_budget() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let polls = Arc::new(AtomicUsize::new(0));
+        let counter = polls.clone();
+        let stream = stream::poll_fn(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(Async::NotReady)
+        });
+        let stream: tokio_signal::IoFuture<_> =
+            Box::new(future::ok(Box::new(stream) as tokio_signal::IoStream<()>));
+        let mut listener = Listener::from_future(stream, InterruptOptions::new().poll_budget(2));
+        for _ in 0..10 {
+            assert_eq!(listener.poll().unwrap(), Async::NotReady);
+        }
+        // At least every third poll, and more if the shared listener received
+        // interrupts in the meantime
+        let polls = polls.load(Ordering::SeqCst);
+        assert!((4..10).contains(&polls), "{} polls", polls);
+    }
+
+    #[test]
+    fn test_suspended() {
+        let events = stream::once(Ok(())).chain(future::empty().into_stream());
+        let mut listener = Listener::from_stream(Box::new(events));
+        listener.signal = true;
+        let guard = crate::suspend_ctrlc();
+        assert_eq!(listener.poll().unwrap(), Async::NotReady);
+        drop(guard);
+    }
+
+    #[test]
+    fn test_force_on_second() {
+        use crate::{CancelFlag, EscalationPolicy};
+
+        let flag = CancelFlag::new();
+        let policy = EscalationPolicy::ForceOnSecond(Duration::from_secs(60));
+        let options = InterruptOptions::from(policy).cancel_on_first(flag.clone());
+        let mut listener = Listener::from_stream(Box::new(stream::iter_ok(vec![(), ()])));
+        listener.options = options;
+        assert_eq!(listener.poll().unwrap(), Async::Ready(()));
+        assert!(flag.is_cancelled());
+
+        // Outside of the window, the interrupt counts as a first one
+        let policy = EscalationPolicy::ForceOnSecond(Duration::from_secs(0));
+        let mut listener = Listener::from_stream(Box::new(stream::iter_ok(vec![(), ()])));
+        listener.options = InterruptOptions::from(policy);
+        listener.swallowed = Some(std::time::Instant::now() - Duration::from_secs(1));
+        assert_eq!(listener.poll().unwrap(), Async::NotReady);
+    }
+
+    #[test]
+    fn test_rearm() {
+        let mut listener = Listener::from_future(ending(), InterruptOptions::new());
+        let polls = future::lazy(move || {
+            assert_eq!(listener.poll()?, Async::Ready(()));
+            // The stream ended, so the real signal is registered instead
+            assert_eq!(listener.poll()?, Async::NotReady);
+            assert!(matches!(listener.state, State::Listening(_)));
+            Ok::<_, crate::IoError>(())
+        });
+        Runtime::new().unwrap().block_on(polls).unwrap();
+    }
+}
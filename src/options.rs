@@ -0,0 +1,162 @@
+//! Configuration of the interrupt handling.
+
+use crate::CancelFlag;
+use std::time::Duration;
+use tokio_reactor::Handle;
+
+/// What to do when the ctrl+c handler fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum HandlerErrorPolicy {
+    /// Fail the wrapped future with an `IoError`.
+    #[default]
+    Propagate,
+    /// Keep running the wrapped future without intercepting ctrl+c.
+    ///
+    /// With the `tracing` feature, a warning is logged.
+    Ignore,
+    /// Keep running the wrapped future and register the handler again after
+    /// `initial` time. The delay doubles after every failed attempt, up to
+    /// `max`.
+    ///
+    /// With the `tracing` feature, a warning is logged for every failure.
+    Reregister { initial: Duration, max: Duration },
+}
+
+/// How many interrupts it takes to interrupt a wrapper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum EscalationPolicy {
+    /// The first interrupt interrupts the wrapper.
+    #[default]
+    Immediate,
+    /// The first interrupt is swallowed and only a second one within the given
+    /// window interrupts the wrapper, like in CLIs saying "press ctrl+c again
+    /// to force quit". After the window, the next interrupt counts as the first
+    /// one again.
+    ///
+    /// The future can learn about the first interrupt through the flag given to
+    /// `InterruptOptions::cancel_on_first`, or from `Status::interrupt_count`.
+    ForceOnSecond(Duration),
+}
+
+/// Options for `ctrlc_as_error_with`.
+///
+/// # Examples
+/// ```
+///     use futures::prelude::*;
+///     use tokio_ctrlc_error::{AsyncCtrlc, HandlerErrorPolicy, InterruptOptions};
+///
+///     // Don't fail if signal handling is forbidden, e.g. by seccomp
+///     let options = InterruptOptions::new().on_install_error(HandlerErrorPolicy::Ignore);
+///     let task = futures::future::ok::<_, failure::Error>(()).ctrlc_as_error_with(options);
+///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+///     rt.block_on(task).unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InterruptOptions {
+    pub(crate) install_error: HandlerErrorPolicy,
+    pub(crate) stream_error: HandlerErrorPolicy,
+    pub(crate) reactor: Option<Handle>,
+    #[cfg(unix)]
+    pub(crate) ignore_own_signals: bool,
+    pub(crate) escalation: EscalationPolicy,
+    pub(crate) first_interrupt: Option<CancelFlag>,
+    pub(crate) poll_budget: u32,
+    pub(crate) grace: Option<Duration>,
+    #[cfg(unix)]
+    pub(crate) signals: Vec<libc::c_int>,
+}
+
+impl InterruptOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set what to do when the ctrl+c handler cannot be installed.
+    pub fn on_install_error(mut self, policy: HandlerErrorPolicy) -> Self {
+        self.install_error = policy;
+        self
+    }
+
+    /// Set what to do when the ctrl+c handler fails after being installed.
+    pub fn on_stream_error(mut self, policy: HandlerErrorPolicy) -> Self {
+        self.stream_error = policy;
+        self
+    }
+
+    /// Drive the signal handling with the given reactor.
+    ///
+    /// By default, ctrl+c is received by the listener shared by the whole
+    /// process, and other signals through the reactor of the runtime polling
+    /// the wrapper, or a background reactor if there is none. With a reactor,
+    /// the wrapper registers its own handler with it, e.g. to tie the signal
+    /// handling to the lifetime of an embedded event loop.
+    pub fn reactor(mut self, handle: Handle) -> Self {
+        self.reactor = Some(handle);
+        self
+    }
+
+    /// Ignore the SIGINTs the process sends to itself, e.g. with `raise` or
+    /// `kill(getpid(), SIGINT)`, so that interrupts triggered by tests are not
+    /// mistaken for the user pressing ctrl+c.
+    ///
+    /// The sender is taken from the `siginfo` of the signal. The signals
+    /// delivered at once are coalesced, and the sender of the last one
+    /// decides for all of them.
+    #[cfg(unix)]
+    pub fn ignore_own_signals(mut self, ignore: bool) -> Self {
+        self.ignore_own_signals = ignore;
+        self
+    }
+
+    /// Set how many interrupts it takes to interrupt the wrapper.
+    pub fn escalation(mut self, policy: EscalationPolicy) -> Self {
+        self.escalation = policy;
+        self
+    }
+
+    /// Cancel `flag` on an interrupt swallowed by the escalation policy, so that
+    /// the future can start a graceful cancellation.
+    pub fn cancel_on_first(mut self, flag: CancelFlag) -> Self {
+        self.first_interrupt = Some(flag);
+        self
+    }
+
+    /// Poll the signal stream only every `polls` polls of the wrapper, or
+    /// when the listener shared by the process has received an interrupt.
+    ///
+    /// For futures polled in tight loops, this replaces polling the stream
+    /// with reading an atomic counter most of the time. The listener wakes
+    /// the wrapper on interrupts, so they are still seen right away. Only
+    /// if the wrapper moves to another task between its polls may it take up
+    /// to `polls` more polls to notice an interrupt. The budget only applies
+    /// to ctrl+c received by the shared listener, not with a `reactor`. 0,
+    /// the default, polls the stream every time.
+    pub fn poll_budget(mut self, polls: u32) -> Self {
+        self.poll_budget = polls;
+        self
+    }
+
+    /// Give the cleanup of a `CtrlcWithCleanup` configured with these options
+    /// `grace` time, instead of the grace period passed to
+    /// `ctrlc_with_cleanup`.
+    pub fn grace(mut self, grace: Duration) -> Self {
+        self.grace = Some(grace);
+        self
+    }
+
+    /// Also intercept `signals` in `signal_as_error_with`, on top of the ones
+    /// passed to it.
+    #[cfg(unix)]
+    pub fn signals(mut self, signals: &[libc::c_int]) -> Self {
+        self.signals.extend_from_slice(signals);
+        self
+    }
+}
+
+impl From<EscalationPolicy> for InterruptOptions {
+    fn from(policy: EscalationPolicy) -> Self {
+        InterruptOptions::new().escalation(policy)
+    }
+}
@@ -0,0 +1,82 @@
+//! Treating ctrl+c as a regular outcome rather than an error.
+
+use crate::{listener::Listener, IoError};
+use futures::prelude::*;
+
+/// The outcome of a future wrapped with `ctrlc_as_result`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ItemOrInterrupt<T> {
+    /// The future completed with this item.
+    Done(T),
+    /// The user pressed ctrl+c before the future completed.
+    Interrupted,
+}
+
+impl<T> ItemOrInterrupt<T> {
+    pub fn is_interrupted(&self) -> bool {
+        match *self {
+            ItemOrInterrupt::Done(_) => false,
+            ItemOrInterrupt::Interrupted => true,
+        }
+    }
+
+    /// Returns the item, or `None` if the future was interrupted.
+    pub fn done(self) -> Option<T> {
+        match self {
+            ItemOrInterrupt::Done(item) => Some(item),
+            ItemOrInterrupt::Interrupted => None,
+        }
+    }
+}
+
+/// Future returned by `ctrlc_as_result`.
+pub struct CtrlcAsResult<F> {
+    ctrlc: Listener,
+    future: F,
+}
+
+impl<F> CtrlcAsResult<F> {
+    pub(crate) fn new(future: F) -> Self {
+        Self::with_listener(Listener::new(), future)
+    }
+
+    fn with_listener(ctrlc: Listener, future: F) -> Self {
+        CtrlcAsResult { ctrlc, future }
+    }
+}
+
+impl<F: Future> Future for CtrlcAsResult<F>
+where
+    F::Error: From<IoError>,
+{
+    type Error = F::Error;
+    type Item = ItemOrInterrupt<F::Item>;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if self.ctrlc.poll()?.is_ready() {
+            return Ok(Async::Ready(ItemOrInterrupt::Interrupted));
+        }
+        let item = futures::try_ready!(self.future.poll());
+        Ok(Async::Ready(ItemOrInterrupt::Done(item)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CtrlcAsResult, ItemOrInterrupt};
+    use crate::listener::Listener;
+    use futures::{future, prelude::*, stream};
+
+    #[test]
+    fn test_ctrlc_as_result() {
+        let ctrlc = Listener::from_stream(Box::new(stream::empty()));
+        let task = CtrlcAsResult::with_listener(ctrlc, future::ok::<_, failure::Error>(3));
+        assert_eq!(task.wait().unwrap(), ItemOrInterrupt::Done(3));
+
+        let ctrlc = Listener::interrupted();
+        let task = CtrlcAsResult::with_listener(ctrlc, future::empty::<u32, failure::Error>());
+        let outcome = task.wait().unwrap();
+        assert!(outcome.is_interrupted());
+        assert_eq!(outcome.done(), None);
+    }
+}
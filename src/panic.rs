@@ -0,0 +1,65 @@
+//! Turning ctrl+c into a panic.
+
+use crate::{listener::Listener, IoError};
+use futures::prelude::*;
+use std::panic;
+
+/// Future returned by `ctrlc_as_panic`.
+pub struct CtrlcAsPanic<F> {
+    ctrlc: Listener,
+    future: F,
+}
+
+impl<F: Future> Future for CtrlcAsPanic<F>
+where
+    F::Error: From<IoError>,
+{
+    type Error = F::Error;
+    type Item = F::Item;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if self.ctrlc.poll()?.is_ready() {
+            panic::panic_any(self.ctrlc.interrupt())
+        } else {
+            self.future.poll()
+        }
+    }
+}
+
+pub trait AsyncCtrlcPanic<F: Future> {
+    /// Intercept ctrl+c during execution and panic in such case.
+    ///
+    /// The panic payload is a `KeyboardInterrupt`, so that it can be told apart
+    /// from other panics with `downcast_ref` on the result of `catch_unwind`.
+    /// This is meant for test harnesses and tools relying on unwinding for
+    /// cleanup.
+    fn ctrlc_as_panic(self) -> CtrlcAsPanic<F>;
+}
+
+impl<F: Future> AsyncCtrlcPanic<F> for F
+where
+    F::Error: From<IoError>,
+{
+    fn ctrlc_as_panic(self) -> CtrlcAsPanic<F> {
+        CtrlcAsPanic {
+            ctrlc: Listener::new(),
+            future: self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AsyncCtrlcPanic;
+    use crate::{listener::Listener, KeyboardInterrupt};
+    use futures::{future, prelude::*};
+    use std::panic::{self, AssertUnwindSafe};
+
+    #[test]
+    fn test_panic() {
+        let mut future = future::empty::<(), failure::Error>().ctrlc_as_panic();
+        future.ctrlc = Listener::interrupted();
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| future.wait())).unwrap_err();
+        assert!(payload.downcast_ref::<KeyboardInterrupt>().is_some());
+    }
+}
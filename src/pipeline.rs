@@ -0,0 +1,209 @@
+//! Staged pipelines stopping between stages on ctrl+c.
+
+use crate::{check::InterruptCheck, IoError, StageInterrupted};
+use futures::{future, prelude::*};
+use std::sync::{Arc, Mutex};
+
+type SharedCheck = Arc<Mutex<InterruptCheck>>;
+
+/// Fail with `StageInterrupted` if ctrl+c has been pressed.
+fn checkpoint<E>(check: &SharedCheck, stage: &'static str) -> Result<(), E>
+where
+    E: From<StageInterrupted> + From<IoError>,
+{
+    if check.lock().unwrap().is_interrupted()? {
+        Err(StageInterrupted::new(stage).into())
+    } else {
+        Ok(())
+    }
+}
+
+/// A pipeline of stages run one after another, see `stage`.
+///
+/// On ctrl+c, the running stage is finished and the pipeline fails with
+/// `StageInterrupted` before starting the next one. Stages added with
+/// `interruptible_stage` are interrupted right away instead. The pipeline is
+/// a future resolving to the result of the last stage. The running stage is
+/// registered with `track_task`.
+pub struct Pipeline<T, E> {
+    check: SharedCheck,
+    future: Box<dyn Future<Item = T, Error = E> + Send>,
+}
+
+/// Start a pipeline with the stage `name`, running the future returned by `f`.
+///
+/// # Examples
+/// ```
+///     use tokio_ctrlc_error::stage;
+///
+///     let pipeline = stage("fetch", || Ok::<_, failure::Error>(vec![1, 2, 3]))
+///         .stage("process", |items| Ok(items.iter().sum::<i32>()))
+///         .stage("upload", |sum| Ok(println!("uploading {}", sum)));
+///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+///     rt.block_on(pipeline).unwrap();
+/// ```
+pub fn stage<F, R>(name: &'static str, f: F) -> Pipeline<R::Item, R::Error>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: IntoFuture,
+    R::Future: Send + 'static,
+    R::Item: Send + 'static,
+    R::Error: From<StageInterrupted> + From<IoError> + Send + 'static,
+{
+    let check = InterruptCheck::new();
+    Pipeline::with_check(check).stage(name, move |()| f())
+}
+
+impl<E> Pipeline<(), E>
+where
+    E: From<StageInterrupted> + From<IoError> + Send + 'static,
+{
+    fn with_check(check: InterruptCheck) -> Self {
+        Pipeline {
+            check: Arc::new(Mutex::new(check)),
+            future: Box::new(future::ok(())),
+        }
+    }
+}
+
+impl<T, E> Pipeline<T, E>
+where
+    T: Send + 'static,
+    E: From<StageInterrupted> + From<IoError> + Send + 'static,
+{
+    /// Add the stage `name`, running the future returned by `f` on the result
+    /// of the previous stage.
+    pub fn stage<F, R>(self, name: &'static str, f: F) -> Pipeline<R::Item, E>
+    where
+        F: FnOnce(T) -> R + Send + 'static,
+        R: IntoFuture<Error = E>,
+        R::Future: Send + 'static,
+        R::Item: Send + 'static,
+    {
+        self.push(name, f, false)
+    }
+
+    /// Like `stage`, but ctrl+c interrupts the stage while it's running.
+    pub fn interruptible_stage<F, R>(self, name: &'static str, f: F) -> Pipeline<R::Item, E>
+    where
+        F: FnOnce(T) -> R + Send + 'static,
+        R: IntoFuture<Error = E>,
+        R::Future: Send + 'static,
+        R::Item: Send + 'static,
+    {
+        self.push(name, f, true)
+    }
+
+    fn push<F, R>(self, name: &'static str, f: F, interruptible: bool) -> Pipeline<R::Item, E>
+    where
+        F: FnOnce(T) -> R + Send + 'static,
+        R: IntoFuture<Error = E>,
+        R::Future: Send + 'static,
+        R::Item: Send + 'static,
+    {
+        let check = self.check.clone();
+        let future = self.future.and_then(move |item| {
+            checkpoint::<E>(&check, name)?;
+            // Listed by `dump_tasks` while running
+            let task = crate::tasks::track_task(name);
+            let future = f(item).into_future().then(move |res| {
+                drop(task);
+                res
+            });
+            Ok::<_, E>(if interruptible {
+                future::Either::A(InterruptibleStage {
+                    check,
+                    name,
+                    future,
+                })
+            } else {
+                future::Either::B(future)
+            })
+        });
+        Pipeline {
+            check: self.check,
+            future: Box::new(future.flatten()),
+        }
+    }
+}
+
+impl<T, E> Future for Pipeline<T, E> {
+    type Error = E;
+    type Item = T;
+
+    fn poll(&mut self) -> Poll<T, E> {
+        self.future.poll()
+    }
+}
+
+struct InterruptibleStage<F> {
+    check: SharedCheck,
+    name: &'static str,
+    future: F,
+}
+
+impl<F: Future> Future for InterruptibleStage<F>
+where
+    F::Error: From<StageInterrupted> + From<IoError>,
+{
+    type Error = F::Error;
+    type Item = F::Item;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        checkpoint::<F::Error>(&self.check, self.name)?;
+        self.future.poll()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{stage, Pipeline};
+    use crate::{check::InterruptCheck, listener::Listener, running_tasks, StageInterrupted};
+    use futures::{future, prelude::*, sync::oneshot};
+    use tokio::runtime::current_thread::Runtime;
+
+    /// A pipeline interrupted when `interrupt` resolves.
+    fn interrupted_by(interrupt: oneshot::Receiver<()>) -> Pipeline<(), failure::Error> {
+        let source = interrupt.map_err(std::io::Error::other).into_stream();
+        let ctrlc = Listener::from_stream(Box::new(source));
+        Pipeline::with_check(InterruptCheck::with_listener(ctrlc))
+    }
+
+    #[test]
+    fn test_stages() {
+        let pipeline = stage("fetch", || Ok::<_, failure::Error>(1))
+            .stage("process", |x| {
+                // Registered while running
+                let running = running_tasks();
+                assert!(running.iter().any(|task| task.name() == "process"));
+                Ok(x + 1)
+            })
+            .interruptible_stage("upload", |x| Ok(x * 2));
+        assert_eq!(Runtime::new().unwrap().block_on(pipeline).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_between_stages() {
+        let (tx, rx) = oneshot::channel();
+        let pipeline = interrupted_by(rx)
+            .stage("fetch", move |()| {
+                tx.send(()).map_err(|()| failure::err_msg("send"))
+            })
+            .stage("process", |()| Ok(()));
+        let err = Runtime::new().unwrap().block_on(pipeline).unwrap_err();
+        let err = err.downcast::<StageInterrupted>().unwrap();
+        assert_eq!(err.stage(), "process");
+    }
+
+    #[test]
+    fn test_interruptible_stage() {
+        let (tx, rx) = oneshot::channel();
+        let pipeline = interrupted_by(rx).interruptible_stage("fetch", move |()| {
+            let _ = tx.send(());
+            future::empty::<(), _>()
+        });
+        let err = Runtime::new().unwrap().block_on(pipeline).unwrap_err();
+        let err = err.downcast::<StageInterrupted>().unwrap();
+        assert_eq!(err.stage(), "fetch");
+    }
+}
@@ -0,0 +1,197 @@
+//! Deciding what to do on each ctrl+c at runtime.
+
+use crate::{listener::Listener, IoError, KeyboardInterrupt};
+#[cfg(feature = "terminal")]
+use futures::sync::oneshot;
+use futures::{prelude::*, try_ready};
+
+/// What to do about an interrupt, see `InterruptPolicy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InterruptDecision {
+    /// Keep running the future as if nothing happened.
+    Ignore,
+    /// Let the future run to completion, then return a `KeyboardInterrupt`
+    /// error in place of its result. Later interrupts are still passed to the
+    /// policy, so that it can force the interruption.
+    Graceful,
+    /// Drop the future and return a `KeyboardInterrupt` error right away, like
+    /// `ctrlc_as_error` does.
+    Force,
+    /// Print the message, e.g. "Really quit? [y/N] ", and read the answer
+    /// from stdin while the future keeps running. The future is forced if the
+    /// answer is yes, on the end of input or on another ctrl+c, and the
+    /// interrupt is ignored otherwise.
+    #[cfg(feature = "terminal")]
+    Prompt(&'static str),
+}
+
+/// A policy consulted by `ctrlc_with_policy` on every ctrl+c.
+///
+/// Unlike `InterruptOptions`, a policy can depend on the state of the
+/// application, e.g. to ignore interrupts during a critical phase. It is
+/// implemented for closures taking the number of interrupts seen so far.
+pub trait InterruptPolicy {
+    /// Decide about the interrupt number `count` seen by the wrapper, counting
+    /// from 1.
+    fn decide(&mut self, count: usize) -> InterruptDecision;
+}
+
+impl<P: FnMut(usize) -> InterruptDecision> InterruptPolicy for P {
+    fn decide(&mut self, count: usize) -> InterruptDecision {
+        self(count)
+    }
+}
+
+/// Future returned by `ctrlc_with_policy`.
+pub struct CtrlcWithPolicy<F, P> {
+    ctrlc: Listener,
+    future: F,
+    policy: P,
+    count: usize,
+    graceful: bool,
+    /// The answer of the user to `InterruptDecision::Prompt`.
+    #[cfg(feature = "terminal")]
+    answer: Option<oneshot::Receiver<crate::terminal::Line>>,
+}
+
+impl<F, P> CtrlcWithPolicy<F, P> {
+    pub(crate) fn new(future: F, policy: P) -> Self {
+        Self::with_listener(Listener::new(), future, policy)
+    }
+
+    fn with_listener(ctrlc: Listener, future: F, policy: P) -> Self {
+        CtrlcWithPolicy {
+            ctrlc,
+            future,
+            policy,
+            count: 0,
+            graceful: false,
+            #[cfg(feature = "terminal")]
+            answer: None,
+        }
+    }
+
+    /// Whether the user confirmed the interruption, once they answered.
+    #[cfg(feature = "terminal")]
+    fn poll_answer(&mut self) -> Poll<bool, IoError> {
+        let answer = match self.answer {
+            Some(ref mut answer) => answer,
+            None => return Ok(Async::NotReady),
+        };
+        let line = match answer.poll() {
+            Ok(Async::Ready(line)) => line,
+            Ok(Async::NotReady) => return Ok(Async::NotReady),
+            Err(oneshot::Canceled) => unreachable!("the stdin reader never exits"),
+        };
+        self.answer = None;
+        Ok(Async::Ready(line?.as_deref().is_none_or(confirmed)))
+    }
+}
+
+/// Whether `answer` to a prompt means yes.
+#[cfg(feature = "terminal")]
+fn confirmed(answer: &str) -> bool {
+    let answer = answer.trim();
+    answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes")
+}
+
+impl<F: Future, P: InterruptPolicy> Future for CtrlcWithPolicy<F, P>
+where
+    F::Error: From<KeyboardInterrupt> + From<IoError>,
+{
+    type Error = F::Error;
+    type Item = F::Item;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        while self.ctrlc.poll()?.is_ready() {
+            self.count += 1;
+            #[cfg(feature = "terminal")]
+            {
+                if self.answer.is_some() {
+                    return Err(self.ctrlc.interrupt().into());
+                }
+            }
+            match self.policy.decide(self.count) {
+                InterruptDecision::Ignore => {}
+                InterruptDecision::Graceful => self.graceful = true,
+                InterruptDecision::Force => return Err(self.ctrlc.interrupt().into()),
+                #[cfg(feature = "terminal")]
+                InterruptDecision::Prompt(message) => {
+                    self.answer = Some(crate::terminal::ask(&format!("\n{}", message)));
+                }
+            }
+        }
+        #[cfg(feature = "terminal")]
+        {
+            if let Async::Ready(true) = self.poll_answer()? {
+                return Err(self.ctrlc.interrupt().into());
+            }
+        }
+        let item = try_ready!(self.future.poll());
+        if self.graceful {
+            Err(self.ctrlc.interrupt().into())
+        } else {
+            Ok(Async::Ready(item))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CtrlcWithPolicy, InterruptDecision};
+    use crate::{listener::Listener, KeyboardInterrupt};
+    use futures::{future, prelude::*, stream};
+
+    fn interrupts(count: usize) -> Listener {
+        let source = stream::iter_ok(vec![(); count]).chain(stream::empty());
+        Listener::from_stream(Box::new(source))
+    }
+
+    #[test]
+    fn test_force_on_second() {
+        let policy = |count| match count {
+            1 => InterruptDecision::Ignore,
+            _ => InterruptDecision::Force,
+        };
+        let future = future::empty::<(), failure::Error>();
+        let mut future = CtrlcWithPolicy::with_listener(interrupts(1), future, policy);
+        assert!(future.poll().unwrap().is_not_ready());
+
+        let future = future::empty::<(), failure::Error>();
+        let future = CtrlcWithPolicy::with_listener(interrupts(2), future, policy);
+        let err = future.wait().unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+    }
+
+    #[test]
+    fn test_graceful() {
+        let policy = |_| InterruptDecision::Graceful;
+        let future = future::ok::<_, failure::Error>(5);
+        let future = CtrlcWithPolicy::with_listener(interrupts(1), future, policy);
+        let err = future.wait().unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+
+        // Errors of the future take precedence
+        let future = future::err::<(), _>(failure::err_msg("failed"));
+        let future = CtrlcWithPolicy::with_listener(interrupts(1), future, policy);
+        let err = future.wait().unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_err());
+    }
+
+    #[cfg(feature = "terminal")]
+    #[test]
+    fn test_prompt() {
+        assert!(super::confirmed("y"));
+        assert!(super::confirmed(" Yes "));
+        assert!(!super::confirmed(""));
+        assert!(!super::confirmed("no"));
+
+        // Another interrupt while asking forces the future
+        let policy = |_| InterruptDecision::Prompt("");
+        let future = future::empty::<(), failure::Error>();
+        let future = CtrlcWithPolicy::with_listener(interrupts(2), future, policy);
+        let err = future.wait().unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+    }
+}
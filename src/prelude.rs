@@ -0,0 +1,37 @@
+//! A prelude for the common use of this crate.
+//!
+//! It has the extension traits adding the wrappers, the errors they return,
+//! the options configuring them and `CtrlcToken`, for the code that checks
+//! for ctrl+c instead of being interrupted. The wrapper futures themselves
+//! are left out, as they're rarely named.
+//!
+//! ```
+//!     use tokio_ctrlc_error::prelude::*;
+//!
+//!     let task = futures::future::ok::<_, Box<dyn std::error::Error + Send + Sync>>(())
+//!         .ctrlc_as_error();
+//!     let mut rt = tokio::runtime::Runtime::new().unwrap();
+//!     match rt.block_on(task) {
+//!         Err(e) if InterruptKind::of(&*e) == Some(InterruptKind::UserInterrupt) => {
+//!             println!("interrupted")
+//!         }
+//!         res => res.unwrap(),
+//!     }
+//! ```
+
+#[cfg(feature = "failure")]
+pub use crate::report;
+#[cfg(feature = "snafu")]
+pub use crate::AsyncCtrlcContext;
+pub use crate::{
+    report_std, AsyncCtrlc, AsyncCtrlcIo, AsyncCtrlcPanic, AsyncCtrlcSink, AsyncCtrlcStream,
+    AsyncTrigger, CtrlcToken, EscalationPolicy, HandlerErrorPolicy, InterruptDecision,
+    InterruptKind, InterruptLike, InterruptOptions, InterruptPolicy, IoError, ItemOrInterrupt,
+    KeyboardInterrupt,
+};
+#[cfg(windows)]
+pub use crate::{AsyncConsole, ConsoleInterrupt};
+#[cfg(feature = "terminal")]
+pub use crate::{AsyncEndOfInput, EndOfInput};
+#[cfg(unix)]
+pub use crate::{AsyncSignal, SignalInterrupt};
@@ -0,0 +1,298 @@
+//! Reporting the final result of the program to the user.
+
+#[cfg(feature = "failure")]
+use crate::find_interrupt;
+use crate::{find_interrupt_std, InterruptKind, InterruptLike};
+use std::{
+    error::Error,
+    fmt::{Display, Write},
+    io::{self, Write as _},
+    iter, process,
+};
+
+/// Report the final result of the program on stderr and return the exit code
+/// to use.
+///
+/// Interrupts are reported with a short message and without a backtrace,
+/// since they are not errors of the program. Other errors are printed with
+/// their cause chain.
+///
+/// # Examples
+/// ```
+///     use tokio_ctrlc_error::{report, AsyncCtrlc};
+///
+///     let task = futures::future::ok::<_, failure::Error>(()).ctrlc_as_error();
+///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+///     let code = report(rt.block_on(task));
+///     assert_eq!(code, 0);
+/// ```
+#[cfg(feature = "failure")]
+pub fn report<T>(res: Result<T, failure::Error>) -> i32 {
+    print(render(res))
+}
+
+/// Like `report`, but for `std::error::Error`s, e.g. `Box<dyn Error + Send + Sync>`.
+///
+/// # Examples
+/// ```
+///     use std::error::Error;
+///     use tokio_ctrlc_error::{report_std, AsyncCtrlc};
+///
+///     let task = futures::future::ok::<_, Box<dyn Error + Send + Sync>>(()).ctrlc_as_error();
+///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+///     let code = report_std(rt.block_on(task));
+///     assert_eq!(code, 0);
+/// ```
+pub fn report_std<T, E>(res: Result<T, E>) -> i32
+where
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    print(render_std(res))
+}
+
+/// Like `report`, but for errors wrapped in `anyhow::Error`.
+#[cfg(feature = "anyhow")]
+pub fn report_anyhow<T>(res: Result<T, anyhow::Error>) -> i32 {
+    report_std(res)
+}
+
+/// Block on `future` and report its result like `report_std`, returning the
+/// exit code: 130 for `KeyboardInterrupt` on Unix, 128 plus the signal number
+/// for `SignalInterrupt`, 0 on success and 1 for other errors.
+///
+/// The future is usually wrapped with `ctrlc_as_error` or `signal_as_error`.
+/// Pass the code to `std::process::exit`, or use `exit_as_signaled` instead to
+/// make the shell see the process as killed by the signal.
+///
+/// # Examples
+/// ```
+///     use std::error::Error;
+///     use tokio_ctrlc_error::{run_interruptible, AsyncCtrlc};
+///
+///     let task = futures::future::ok::<_, Box<dyn Error + Send + Sync>>(()).ctrlc_as_error();
+///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+///     assert_eq!(run_interruptible(&mut rt, task), 0);
+/// ```
+#[cfg(feature = "tokio")]
+pub fn run_interruptible<F>(rt: &mut tokio::runtime::Runtime, future: F) -> i32
+where
+    F: futures::Future + Send + 'static,
+    F::Item: Send + 'static,
+    F::Error: Into<Box<dyn Error + Send + Sync>> + Send + 'static,
+{
+    report_std(rt.block_on(future))
+}
+
+/// Report the final result of the program like `report_std` and exit the
+/// process, dying of the signal instead if it was interrupted by one.
+///
+/// Shells and process supervisors tell a process killed by a signal from one
+/// exiting with an error: e.g. a shell running a script stops it when one of
+/// its commands is killed by SIGINT. Only the interrupts of this crate are
+/// re-raised, with the signal they were caused by, see
+/// `InterruptKind::signal`. The disposition of the signal is reset to the
+/// default, so the handlers of this crate are bypassed. On other platforms, or
+/// if the signal doesn't terminate the process, this exits with the code
+/// returned by `report_std`.
+///
+/// # Examples
+/// ```no_run
+///     use std::error::Error;
+///     use tokio_ctrlc_error::{exit_as_signaled, AsyncCtrlc};
+///
+///     let task = futures::future::ok::<_, Box<dyn Error + Send + Sync>>(()).ctrlc_as_error();
+///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+///     exit_as_signaled(rt.block_on(task));
+/// ```
+pub fn exit_as_signaled<T, E>(res: Result<T, E>) -> !
+where
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    let res = res.map_err(Into::into);
+    #[cfg(unix)]
+    let signal = res
+        .as_ref()
+        .err()
+        .and_then(|err| InterruptKind::of(&**err))
+        .and_then(InterruptKind::signal);
+    let code = report_std(res);
+    // Dying of a signal skips the buffered output
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+    #[cfg(unix)]
+    {
+        if let Some(signal) = signal {
+            unsafe {
+                libc::signal(signal, libc::SIG_DFL);
+                libc::raise(signal);
+            }
+        }
+    }
+    process::exit(code)
+}
+
+fn print((message, code): (Option<String>, i32)) -> i32 {
+    if let Some(message) = message {
+        eprint!("{}", message);
+    }
+    code
+}
+
+#[cfg(feature = "failure")]
+fn render<T>(res: Result<T, failure::Error>) -> (Option<String>, i32) {
+    let err = match res {
+        Ok(_) => return (None, 0),
+        Err(err) => err,
+    };
+    match find_interrupt(&err) {
+        Some(interrupt) => render_interrupt(interrupt),
+        None => (Some(render_error(&err, err.iter_causes())), 1),
+    }
+}
+
+fn render_std<T, E>(res: Result<T, E>) -> (Option<String>, i32)
+where
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    let err: Box<dyn Error + Send + Sync> = match res {
+        Ok(_) => return (None, 0),
+        Err(err) => err.into(),
+    };
+    let err: &(dyn Error + 'static) = &*err;
+    match find_interrupt_std(err) {
+        Some(interrupt) => render_interrupt(interrupt),
+        None => {
+            let causes = iter::successors(err.source(), |&e| e.source());
+            (Some(render_error(err, causes)), 1)
+        }
+    }
+}
+
+fn render_interrupt(interrupt: &dyn InterruptLike) -> (Option<String>, i32) {
+    let message = match interrupt.interrupt_kind() {
+        InterruptKind::UserInterrupt => Some("Interrupted by user\n".to_owned()),
+        #[cfg(unix)]
+        InterruptKind::Signal(_) => Some(capitalized(interrupt)),
+        InterruptKind::Trigger | InterruptKind::Deadline => Some(capitalized(interrupt)),
+        #[cfg(windows)]
+        InterruptKind::Console(_) => Some(capitalized(interrupt)),
+        _ => None,
+    };
+    (message, interrupt.exit_code())
+}
+
+fn render_error<C: Display>(err: &dyn Display, causes: impl Iterator<Item = C>) -> String {
+    let mut message = format!("Error: {}\n", err);
+    for cause in causes {
+        // Writing to a string cannot fail
+        let _ = writeln!(message, "Caused by: {}", cause);
+    }
+    message
+}
+
+/// The message of `interrupt`, as a sentence of its own.
+fn capitalized(interrupt: &dyn InterruptLike) -> String {
+    let mut message = format!("{}\n", interrupt);
+    message[..1].make_ascii_uppercase();
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_std;
+    #[cfg(unix)]
+    use crate::SignalInterrupt;
+    use crate::{DeadlineExceeded, KeyboardInterrupt, TriggerInterrupt};
+    use std::{error::Error, fmt, io, time::Duration};
+
+    #[cfg(feature = "failure")]
+    #[test]
+    fn test_render() {
+        use super::render;
+        use failure::ResultExt;
+
+        assert_eq!(render(Ok::<_, failure::Error>(())), (None, 0));
+
+        let res: Result<(), failure::Error> = Err(KeyboardInterrupt::new().into());
+        let (message, code) = render(res);
+        assert_eq!(message.unwrap(), "Interrupted by user\n");
+        assert_ne!(code, 0);
+
+        let res: Result<(), _> = Err(failure::err_msg("disk full"));
+        let res = res.context("cannot save").map_err(failure::Error::from);
+        let (message, code) = render(res);
+        assert_eq!(
+            message.unwrap(),
+            "Error: cannot save\nCaused by: disk full\n"
+        );
+        assert_eq!(code, 1);
+    }
+
+    #[derive(Debug)]
+    struct CannotSave(io::Error);
+
+    impl fmt::Display for CannotSave {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("cannot save")
+        }
+    }
+
+    impl Error for CannotSave {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn test_render_std() {
+        assert_eq!(render_std(Ok::<_, io::Error>(())), (None, 0));
+
+        let (message, code) = render_std(Err::<(), _>(KeyboardInterrupt::new()));
+        assert_eq!(message.unwrap(), "Interrupted by user\n");
+        assert_ne!(code, 0);
+
+        let err = CannotSave(io::Error::other("disk full"));
+        let (message, code) = render_std(Err::<(), _>(err));
+        assert_eq!(
+            message.unwrap(),
+            "Error: cannot save\nCaused by: disk full\n"
+        );
+        assert_eq!(code, 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_render_signal() {
+        let (message, code) = render_std(Err::<(), _>(SignalInterrupt::new(libc::SIGTERM)));
+        assert_eq!(message.unwrap(), "Interrupted by SIGTERM\n");
+        assert_eq!(code, 143);
+    }
+
+    #[test]
+    fn test_render_trigger() {
+        let (message, code) = render_std(Err::<(), _>(TriggerInterrupt::new("deadline")));
+        assert_eq!(message.unwrap(), "Interrupted by trigger `deadline`\n");
+        assert_eq!(code, 1);
+
+        let err = DeadlineExceeded::new(Duration::from_secs(5));
+        let (message, code) = render_std(Err::<(), _>(err));
+        assert_eq!(message.unwrap(), "Deadline exceeded after 5s\n");
+        assert_eq!(code, 124);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_run_interruptible() {
+        use super::run_interruptible;
+        use crate::{CtrlcAsError, InterruptKind};
+        use futures::{future, stream};
+
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        let task = CtrlcAsError::with_signal_source(
+            future::empty::<(), Box<dyn Error + Send + Sync>>(),
+            stream::once(Ok(())),
+        );
+        let code = run_interruptible(&mut rt, task);
+        assert_eq!(code, InterruptKind::UserInterrupt.exit_code());
+    }
+}
@@ -0,0 +1,194 @@
+//! Running a task again after its attempts fail or are interrupted.
+
+use crate::{listener::Listener, IoError, KeyboardInterrupt};
+use futures::prelude::*;
+
+/// Why an attempt of `ctrlc_retry` failed, see `RetryPolicy`.
+#[derive(Debug)]
+pub enum AttemptFailure<'a, E> {
+    /// The user pressed ctrl+c during the attempt.
+    Interrupted,
+    /// The attempt failed with this error.
+    Failed(&'a E),
+}
+
+/// A policy consulted by `ctrlc_retry` after every failed attempt.
+///
+/// It is implemented for closures taking the number of the attempt, counting
+/// from 1, and why it failed.
+pub trait RetryPolicy<E> {
+    /// Whether to start another attempt after the attempt number `attempt`
+    /// failed. Otherwise, the error of the attempt is returned, or
+    /// `KeyboardInterrupt` if it was interrupted.
+    fn retry(&mut self, attempt: usize, failure: AttemptFailure<'_, E>) -> bool;
+}
+
+impl<E, P: FnMut(usize, AttemptFailure<'_, E>) -> bool> RetryPolicy<E> for P {
+    fn retry(&mut self, attempt: usize, failure: AttemptFailure<'_, E>) -> bool {
+        self(attempt, failure)
+    }
+}
+
+/// Retry up to the given number of times on errors, and stop on ctrl+c.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryErrors(pub usize);
+
+impl<E> RetryPolicy<E> for RetryErrors {
+    fn retry(&mut self, attempt: usize, failure: AttemptFailure<'_, E>) -> bool {
+        match failure {
+            AttemptFailure::Interrupted => false,
+            AttemptFailure::Failed(_) => attempt <= self.0,
+        }
+    }
+}
+
+/// Future returned by `ctrlc_retry`.
+pub struct CtrlcRetry<G, F, P> {
+    ctrlc: Listener,
+    factory: G,
+    /// The current attempt, started on the first poll.
+    future: Option<F>,
+    attempt: usize,
+    policy: P,
+}
+
+impl<G, F, P> CtrlcRetry<G, F, P> {
+    fn with_listener(ctrlc: Listener, factory: G, policy: P) -> Self {
+        CtrlcRetry {
+            ctrlc,
+            factory,
+            future: None,
+            attempt: 0,
+            policy,
+        }
+    }
+}
+
+impl<G, F, P> Future for CtrlcRetry<G, F, P>
+where
+    G: FnMut() -> F,
+    F: Future,
+    F::Error: From<KeyboardInterrupt> + From<IoError>,
+    P: RetryPolicy<F::Error>,
+{
+    type Error = F::Error;
+    type Item = F::Item;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            let future = match self.future {
+                Some(ref mut future) => future,
+                None => {
+                    self.attempt += 1;
+                    self.future.get_or_insert((self.factory)())
+                }
+            };
+            // The listener keeps listening between the attempts, so that an
+            // interrupt is never missed
+            let res = if self.ctrlc.poll()?.is_ready() {
+                None
+            } else {
+                match future.poll() {
+                    Ok(Async::Ready(item)) => return Ok(Async::Ready(item)),
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Err(e) => Some(e),
+                }
+            };
+            self.future = None;
+            let failure = match res {
+                Some(ref e) => AttemptFailure::Failed(e),
+                None => AttemptFailure::Interrupted,
+            };
+            if !self.policy.retry(self.attempt, failure) {
+                return Err(match res {
+                    Some(e) => e,
+                    None => self.ctrlc.interrupt().into(),
+                });
+            }
+        }
+    }
+}
+
+/// Run the futures created by `factory` until one of them succeeds, or the
+/// `policy` gives up, interrupting each attempt on ctrl+c.
+///
+/// Use it for idempotent tasks, where an interrupted attempt can just be
+/// started over, e.g. to cancel a stuck request with ctrl+c and send it
+/// again, while the policy counts and stops on the repeated ones.
+///
+/// # Examples
+/// ```
+///     use tokio_ctrlc_error::{ctrlc_retry, RetryErrors};
+///
+///     let mut attempts = 0;
+///     let task = ctrlc_retry(
+///         move || {
+///             attempts += 1;
+///             if attempts < 3 {
+///                 futures::future::err(failure::err_msg("server busy"))
+///             } else {
+///                 futures::future::ok(attempts)
+///             }
+///         },
+///         RetryErrors(5),
+///     );
+///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+///     assert_eq!(rt.block_on(task).unwrap(), 3);
+/// ```
+pub fn ctrlc_retry<G, F, P>(factory: G, policy: P) -> CtrlcRetry<G, F, P>
+where
+    G: FnMut() -> F,
+    F: Future,
+    F::Error: From<KeyboardInterrupt> + From<IoError>,
+    P: RetryPolicy<F::Error>,
+{
+    CtrlcRetry::with_listener(Listener::new(), factory, policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AttemptFailure, CtrlcRetry, RetryErrors};
+    use crate::{listener::Listener, KeyboardInterrupt};
+    use futures::{future, prelude::*, stream};
+
+    fn interrupts(count: usize) -> Listener {
+        let source = stream::iter_ok(vec![(); count]).chain(stream::empty());
+        Listener::from_stream(Box::new(source))
+    }
+
+    #[test]
+    fn test_retry_errors() {
+        let mut attempts = 0;
+        let factory = || {
+            attempts += 1;
+            future::err::<(), _>(failure::err_msg("failed"))
+        };
+        let task = CtrlcRetry::with_listener(interrupts(0), factory, RetryErrors(2));
+        let err = task.wait().unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_err());
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn test_retry_interrupted() {
+        let factory = future::empty::<(), failure::Error>;
+        let task = CtrlcRetry::with_listener(interrupts(1), factory, RetryErrors(5));
+        let err = task.wait().unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+
+        // Interrupted attempts can be retried as well
+        let mut restarted = false;
+        let factory = move || {
+            let attempt = if restarted {
+                future::Either::A(future::ok(()))
+            } else {
+                future::Either::B(future::empty::<(), failure::Error>())
+            };
+            restarted = true;
+            attempt
+        };
+        let policy = |_, _: AttemptFailure<'_, failure::Error>| true;
+        let task = CtrlcRetry::with_listener(interrupts(1), factory, policy);
+        task.wait().unwrap();
+    }
+}
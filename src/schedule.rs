@@ -0,0 +1,192 @@
+//! Draining timer-driven queues on ctrl+c.
+
+use crate::{listener::Listener, IoError};
+use futures::{prelude::*, try_ready};
+use std::{
+    collections::{HashMap, VecDeque},
+    marker::PhantomData,
+    time::{Duration, Instant},
+};
+use tokio_timer::delay_queue::{self, DelayQueue};
+
+/// A key of an entry of `CtrlcDelayQueue`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueueKey(u64);
+
+/// An item yielded by `CtrlcDelayQueue`.
+#[derive(Debug)]
+pub struct Scheduled<T> {
+    pub value: T,
+    pub deadline: Instant,
+}
+
+struct Entry<T> {
+    key: delay_queue::Key,
+    value: T,
+    deadline: Instant,
+}
+
+/// Like `tokio_timer::DelayQueue`, but drained on ctrl+c.
+///
+/// Yields the values as their deadlines expire. On ctrl+c, it stops waiting
+/// and yields all the remaining values immediately, in the order of their
+/// deadlines, and then ends. This allows a scheduler to persist its pending
+/// work before exiting.
+pub struct CtrlcDelayQueue<T, E> {
+    ctrlc: Listener,
+    queue: DelayQueue<u64>,
+    entries: HashMap<u64, Entry<T>>,
+    next_id: u64,
+    drained: Option<VecDeque<Scheduled<T>>>,
+    error: PhantomData<fn() -> E>,
+}
+
+impl<T, E> CtrlcDelayQueue<T, E> {
+    pub fn new() -> Self {
+        Self::with_listener(Listener::new())
+    }
+
+    fn with_listener(ctrlc: Listener) -> Self {
+        CtrlcDelayQueue {
+            ctrlc,
+            queue: DelayQueue::new(),
+            entries: HashMap::new(),
+            next_id: 0,
+            drained: None,
+            error: PhantomData,
+        }
+    }
+
+    /// Schedule `value` to be yielded at `deadline`.
+    pub fn insert_at(&mut self, value: T, deadline: Instant) -> QueueKey {
+        let id = self.next_id;
+        self.next_id += 1;
+        match self.drained {
+            // Already interrupted, so there is nothing to wait for
+            Some(ref mut drained) => drained.push_back(Scheduled { value, deadline }),
+            None => {
+                let key = self.queue.insert_at(id, deadline);
+                let entry = Entry {
+                    key,
+                    value,
+                    deadline,
+                };
+                self.entries.insert(id, entry);
+            }
+        }
+        QueueKey(id)
+    }
+
+    /// Schedule `value` to be yielded after `timeout`.
+    pub fn insert(&mut self, value: T, timeout: Duration) -> QueueKey {
+        self.insert_at(value, Instant::now() + timeout)
+    }
+
+    /// Remove a scheduled value, if it hasn't been yielded yet.
+    pub fn remove(&mut self, key: &QueueKey) -> Option<T> {
+        let entry = self.entries.remove(&key.0)?;
+        self.queue.remove(&entry.key);
+        Some(entry.value)
+    }
+
+    /// The number of values not yielded yet.
+    pub fn len(&self) -> usize {
+        self.entries.len() + self.drained.as_ref().map_or(0, VecDeque::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the queue has been interrupted and is being drained.
+    pub fn is_interrupted(&self) -> bool {
+        self.drained.is_some()
+    }
+
+    fn drain(&mut self) {
+        self.queue.clear();
+        let mut remaining: Vec<_> = self
+            .entries
+            .drain()
+            .map(|(_, entry)| Scheduled {
+                value: entry.value,
+                deadline: entry.deadline,
+            })
+            .collect();
+        remaining.sort_by_key(|scheduled| scheduled.deadline);
+        self.drained = Some(remaining.into());
+    }
+}
+
+impl<T, E> Default for CtrlcDelayQueue<T, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, E> Stream for CtrlcDelayQueue<T, E>
+where
+    E: From<IoError> + From<tokio_timer::Error>,
+{
+    type Error = E;
+    type Item = Scheduled<T>;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if self.drained.is_none() && self.ctrlc.poll()?.is_ready() {
+            self.drain();
+        }
+        if let Some(ref mut drained) = self.drained {
+            return Ok(Async::Ready(drained.pop_front()));
+        }
+
+        loop {
+            let id = match try_ready!(self.queue.poll()) {
+                Some(expired) => expired.into_inner(),
+                // The queue may get new entries later
+                None => return Ok(Async::NotReady),
+            };
+            if let Some(entry) = self.entries.remove(&id) {
+                let scheduled = Scheduled {
+                    value: entry.value,
+                    deadline: entry.deadline,
+                };
+                return Ok(Async::Ready(Some(scheduled)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CtrlcDelayQueue;
+    use crate::listener::Listener;
+    use futures::prelude::*;
+    use std::time::Duration;
+    use tokio::runtime::current_thread::Runtime;
+
+    #[test]
+    fn test_expire() {
+        let mut queue = CtrlcDelayQueue::<_, failure::Error>::new();
+        queue.insert("late", Duration::from_secs(3600));
+        queue.insert("early", Duration::from_millis(1));
+        let mut rt = Runtime::new().unwrap();
+        let (item, queue) = rt.block_on(queue.into_future()).map_err(|e| e.0).unwrap();
+        assert_eq!(item.unwrap().value, "early");
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn test_drain() {
+        let ctrlc = Listener::interrupted();
+        let mut queue = CtrlcDelayQueue::<_, failure::Error>::with_listener(ctrlc);
+        queue.insert("second", Duration::from_secs(7200));
+        let removed = queue.insert("removed", Duration::from_secs(1800));
+        queue.insert("first", Duration::from_secs(3600));
+        assert_eq!(queue.remove(&removed), Some("removed"));
+
+        let mut rt = Runtime::new().unwrap();
+        let items = rt.block_on(queue.collect()).unwrap();
+        let values: Vec<_> = items.into_iter().map(|item| item.value).collect();
+        assert_eq!(values, vec!["first", "second"]);
+    }
+}
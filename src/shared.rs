@@ -0,0 +1,286 @@
+//! The ctrl+c listener shared by all the wrappers of the process.
+//!
+//! A single `tokio_signal::ctrl_c` stream is driven by a background thread
+//! with its own reactor, and every wrapper subscribes to it. This way the
+//! handler is registered once, and all the wrappers see the same interrupts,
+//! whichever runtime they're polled on. The handler is registered on that
+//! thread as well, so that subscribing never blocks the runtime.
+
+use futures::{future, prelude::*, task::AtomicTask};
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
+    thread,
+};
+use tokio_reactor::Reactor;
+use tokio_signal::{IoFuture, IoStream};
+
+/// The current listener, replaced by the next subscription once it fails.
+static SHARED: Mutex<Option<Arc<Shared>>> = Mutex::new(None);
+/// The number of interrupts received by all the listeners so far.
+static RECEIVED: AtomicUsize = AtomicUsize::new(0);
+
+struct Shared {
+    /// The number of interrupts received so far.
+    count: AtomicUsize,
+    /// Whether the handler has been registered.
+    registered: AtomicBool,
+    /// Whether the listener failed, so that the subscribers only take the
+    /// lock for the error.
+    failed: AtomicBool,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    /// The error of the listener, kept for the subscribers still listening.
+    error: Option<(io::ErrorKind, String)>,
+    /// The tasks of the subscribers, woken on every interrupt.
+    tasks: Vec<Weak<AtomicTask>>,
+}
+
+impl Shared {
+    fn new() -> Self {
+        Shared {
+            count: AtomicUsize::new(0),
+            registered: AtomicBool::new(false),
+            failed: AtomicBool::new(false),
+            inner: Mutex::new(Inner {
+                error: None,
+                tasks: Vec::new(),
+            }),
+        }
+    }
+
+    fn fail(&self, e: io::Error) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.error = Some((e.kind(), e.to_string()));
+        self.failed.store(true, Ordering::Release);
+        inner.notify();
+    }
+
+    fn register(&self) {
+        self.registered.store(true, Ordering::Release);
+        self.inner.lock().unwrap().notify();
+    }
+
+    fn interrupt(&self) {
+        self.count.fetch_add(1, Ordering::AcqRel);
+        // After the count, so that the wrappers skipping polls see the
+        // interrupt in their subscription once they see it here
+        RECEIVED.fetch_add(1, Ordering::AcqRel);
+        self.inner.lock().unwrap().notify();
+    }
+
+    /// The error of the listener, if it failed.
+    fn error(&self) -> Option<io::Error> {
+        if !self.failed.load(Ordering::Acquire) {
+            return None;
+        }
+        self.inner.lock().unwrap().error()
+    }
+}
+
+impl Inner {
+    fn notify(&mut self) {
+        self.tasks.retain(|task| match task.upgrade() {
+            Some(task) => {
+                task.notify();
+                true
+            }
+            None => false,
+        });
+    }
+
+    /// The error of the listener, if it failed.
+    fn error(&self) -> Option<io::Error> {
+        self.error
+            .as_ref()
+            .map(|&(kind, ref msg)| io::Error::new(kind, msg.clone()))
+    }
+}
+
+/// Returns the current listener, starting one if there's none or it failed.
+///
+/// The handler is registered by the background thread, the subscribers wait
+/// for the registration without blocking.
+fn shared() -> io::Result<Arc<Shared>> {
+    let mut current = SHARED.lock().unwrap();
+    if let Some(ref shared) = *current {
+        if !shared.failed.load(Ordering::Acquire) {
+            return Ok(shared.clone());
+        }
+    }
+    let shared = Arc::new(Shared::new());
+    let listener = shared.clone();
+    thread::Builder::new()
+        .name("ctrlc".to_owned())
+        .spawn(move || {
+            if let Err(e) = listen(&listener) {
+                listener.fail(e);
+            }
+        })?;
+    *current = Some(shared.clone());
+    Ok(shared)
+}
+
+/// Register the handler and forward the interrupts to the subscribers, until
+/// the stream fails.
+fn listen(listener: &Shared) -> io::Result<()> {
+    let reactor = Reactor::new()?;
+    let handle = reactor.handle();
+    // Keeps the reactor running as long as the thread listens
+    let _background = reactor.background()?;
+    let stream = tokio_signal::ctrl_c_handle(&handle).wait()?;
+    listener.register();
+    for event in stream.wait() {
+        event?;
+        listener.interrupt();
+    }
+    Err(io::Error::other("the ctrl+c stream ended"))
+}
+
+/// Returns the number of interrupts received by the shared listener, even
+/// while no wrapper is subscribed.
+pub(crate) fn received() -> usize {
+    RECEIVED.load(Ordering::Acquire)
+}
+
+/// Subscribe to the shared listener on the first poll, resolving to the
+/// stream of the interrupts received since once the handler is registered.
+pub(crate) fn subscribe() -> IoFuture<IoStream<()>> {
+    Box::new(
+        future::lazy(|| {
+            let shared = shared()?;
+            let task = Arc::new(AtomicTask::new());
+            let seen = shared.count.load(Ordering::Acquire);
+            shared
+                .inner
+                .lock()
+                .unwrap()
+                .tasks
+                .push(Arc::downgrade(&task));
+            Ok(Subscription { shared, task, seen })
+        })
+        .and_then(|subscription| Registration(Some(subscription))),
+    )
+}
+
+/// Waits for the registration of the handler by the shared listener.
+struct Registration(Option<Subscription>);
+
+impl Future for Registration {
+    type Item = IoStream<()>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<IoStream<()>, io::Error> {
+        {
+            let subscription = self.0.as_ref().expect("polled after completion");
+            subscription.task.register();
+            if !subscription.shared.registered.load(Ordering::Acquire) {
+                return match subscription.shared.error() {
+                    Some(e) => Err(e),
+                    None => Ok(Async::NotReady),
+                };
+            }
+        }
+        Ok(Async::Ready(Box::new(self.0.take().unwrap())))
+    }
+}
+
+/// A stream yielding an item for every interrupt seen by the shared listener.
+struct Subscription {
+    shared: Arc<Shared>,
+    task: Arc<AtomicTask>,
+    /// The number of interrupts yielded, counting the ones from before the
+    /// subscription.
+    seen: usize,
+}
+
+impl Stream for Subscription {
+    type Error = io::Error;
+    type Item = ();
+
+    fn poll(&mut self) -> Poll<Option<()>, io::Error> {
+        self.task.register();
+        if self.shared.count.load(Ordering::Acquire) > self.seen {
+            self.seen += 1;
+            return Ok(Async::Ready(Some(())));
+        }
+        match self.shared.error() {
+            Some(e) => Err(e),
+            None => Ok(Async::NotReady),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Registration, Shared, Subscription};
+    use futures::{future, prelude::*, task::AtomicTask};
+    use std::{
+        io,
+        sync::{atomic::AtomicUsize, Arc},
+    };
+
+    #[test]
+    fn test_subscription() {
+        let shared = Arc::new(Shared {
+            count: AtomicUsize::new(1),
+            ..Shared::new()
+        });
+        let mut first = Subscription {
+            shared: shared.clone(),
+            task: Arc::new(AtomicTask::new()),
+            seen: 1,
+        };
+        shared.interrupt();
+        shared.interrupt();
+        let mut second = Subscription {
+            shared: shared.clone(),
+            task: Arc::new(AtomicTask::new()),
+            seen: 3,
+        };
+        shared.fail(io::Error::other("failed"));
+        let mut first = first.by_ref().wait();
+        // Every interrupt is yielded, then the error
+        assert!(first.next().unwrap().is_ok());
+        assert!(first.next().unwrap().is_ok());
+        assert!(first.next().unwrap().is_err());
+        assert!(second.by_ref().wait().next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_registration() {
+        let shared = Arc::new(Shared::new());
+        let subscription = |shared: &Arc<Shared>| Subscription {
+            shared: shared.clone(),
+            task: Arc::new(AtomicTask::new()),
+            seen: 0,
+        };
+        let mut registration = Registration(Some(subscription(&shared)));
+        future::poll_fn(|| {
+            assert!(registration.poll().unwrap().is_not_ready());
+            Ok::<_, ()>(Async::Ready(()))
+        })
+        .wait()
+        .unwrap();
+        shared.register();
+        assert!(registration.wait().is_ok());
+
+        let shared = Arc::new(Shared::new());
+        shared.fail(io::Error::other("failed"));
+        assert!(Registration(Some(subscription(&shared))).wait().is_err());
+    }
+
+    #[test]
+    fn test_status_count() {
+        // Counted without any wrapper being polled
+        let before = crate::status().interrupt_count();
+        Shared::new().interrupt();
+        assert!(crate::status().interrupt_count() > before);
+        assert!(crate::status().interrupted());
+    }
+}
@@ -0,0 +1,87 @@
+//! Interrupting futures on Unix signals other than SIGINT.
+
+use crate::{listener::Listener, InterruptOptions, IoError, SignalInterrupt};
+use futures::prelude::*;
+
+/// Future returned by `signal_as_error`.
+pub struct SignalAsError<F> {
+    listeners: Vec<(libc::c_int, Listener)>,
+    future: F,
+}
+
+impl<F: Future> Future for SignalAsError<F>
+where
+    F::Error: From<SignalInterrupt> + From<IoError>,
+{
+    type Error = F::Error;
+    type Item = F::Item;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        for (signal, listener) in &mut self.listeners {
+            if listener.poll()?.is_ready() {
+                return Err(SignalInterrupt::new(*signal).into());
+            }
+        }
+        self.future.poll()
+    }
+}
+
+pub trait AsyncSignal<F: Future> {
+    /// Intercept any of the `signals` during execution and return a
+    /// `SignalInterrupt` error telling which one was received.
+    ///
+    /// This treats e.g. SIGTERM and SIGHUP of a daemon the way `ctrlc_as_error`
+    /// treats ctrl+c. For SIGINT, prefer `ctrlc_as_error`, which returns a
+    /// `KeyboardInterrupt`.
+    ///
+    /// # Examples
+    /// ```
+    ///     use futures::prelude::*;
+    ///     use tokio_ctrlc_error::AsyncSignal;
+    ///
+    ///     let task = futures::future::ok::<_, failure::Error>(())
+    ///         .signal_as_error(&[libc::SIGTERM, libc::SIGHUP]);
+    ///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+    ///     rt.block_on(task).unwrap();
+    /// ```
+    fn signal_as_error(self, signals: &[libc::c_int]) -> SignalAsError<F>;
+
+    /// Like `signal_as_error`, but configured with `options`. The signals set
+    /// with `InterruptOptions::signals` are intercepted as well.
+    fn signal_as_error_with<O: Into<InterruptOptions>>(
+        self,
+        signals: &[libc::c_int],
+        options: O,
+    ) -> SignalAsError<F>;
+}
+
+impl<F: Future> AsyncSignal<F> for F
+where
+    F::Error: From<SignalInterrupt> + From<IoError>,
+{
+    fn signal_as_error(self, signals: &[libc::c_int]) -> SignalAsError<F> {
+        self.signal_as_error_with(signals, InterruptOptions::default())
+    }
+
+    fn signal_as_error_with<O: Into<InterruptOptions>>(
+        self,
+        signals: &[libc::c_int],
+        options: O,
+    ) -> SignalAsError<F> {
+        let options = options.into();
+        let mut all = signals.to_vec();
+        for &signal in &options.signals {
+            if !all.contains(&signal) {
+                all.push(signal);
+            }
+        }
+        let listeners = all
+            .into_iter()
+            .map(|signal| (signal, Listener::for_signal(signal, options.clone())))
+            .collect();
+        SignalAsError {
+            listeners,
+            future: self,
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Interrupting sinks, e.g. ones flushing to a remote service.
+
+use crate::{listener::Listener, IoError, KeyboardInterrupt};
+use futures::prelude::*;
+
+/// Sink returned by `ctrlc_sink`.
+pub struct CtrlcSink<S> {
+    ctrlc: Listener,
+    interrupted: bool,
+    inner: S,
+}
+
+impl<S> CtrlcSink<S> {
+    fn with_listener(ctrlc: Listener, inner: S) -> Self {
+        CtrlcSink {
+            ctrlc,
+            interrupted: false,
+            inner,
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Sink> CtrlcSink<S>
+where
+    S::SinkError: From<KeyboardInterrupt> + From<IoError>,
+{
+    /// Fails with `KeyboardInterrupt` once ctrl+c has been pressed.
+    fn check(&mut self) -> Result<(), S::SinkError> {
+        if !self.interrupted {
+            self.interrupted = self.ctrlc.poll()?.is_ready();
+        }
+        if self.interrupted {
+            Err(self.ctrlc.interrupt().into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<S: Sink> Sink for CtrlcSink<S>
+where
+    S::SinkError: From<KeyboardInterrupt> + From<IoError>,
+{
+    type SinkError = S::SinkError;
+    type SinkItem = S::SinkItem;
+
+    fn start_send(&mut self, item: S::SinkItem) -> StartSend<S::SinkItem, S::SinkError> {
+        self.check()?;
+        self.inner.start_send(item)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), S::SinkError> {
+        self.check()?;
+        self.inner.poll_complete()
+    }
+
+    fn close(&mut self) -> Poll<(), S::SinkError> {
+        self.check()?;
+        self.inner.close()
+    }
+}
+
+impl<S: Stream> Stream for CtrlcSink<S> {
+    type Error = S::Error;
+    type Item = S::Item;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        self.inner.poll()
+    }
+}
+
+pub trait AsyncCtrlcSink: Sink + Sized {
+    /// Fail sending and flushing with `KeyboardInterrupt` once ctrl+c has
+    /// been pressed.
+    ///
+    /// This interrupts e.g. a `send_all` stuck on a slow sink, like
+    /// `ctrlc_as_error` would for the whole future, but keeps the sink: every
+    /// operation fails after ctrl+c, including `close`, and the inner sink
+    /// can still be closed through `get_mut`. The stream half of a duplex
+    /// sink, e.g. a framed connection, is not interrupted.
+    ///
+    /// # Examples
+    /// ```
+    ///     use futures::prelude::*;
+    ///     use tokio_ctrlc_error::AsyncCtrlcSink;
+    ///
+    ///     let (tx, rx) = futures::sync::mpsc::unbounded::<u32>();
+    ///     let sink = tx.sink_map_err(|_| failure::err_msg("receiver gone")).ctrlc_sink();
+    ///     let task = sink.send_all(futures::stream::iter_ok::<_, failure::Error>(0..3));
+    ///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+    ///     rt.block_on(task).unwrap();
+    ///     assert_eq!(rx.collect().wait().unwrap(), vec![0, 1, 2]);
+    /// ```
+    fn ctrlc_sink(self) -> CtrlcSink<Self>;
+}
+
+impl<S: Sink> AsyncCtrlcSink for S
+where
+    S::SinkError: From<KeyboardInterrupt> + From<IoError>,
+{
+    fn ctrlc_sink(self) -> CtrlcSink<Self> {
+        CtrlcSink::with_listener(Listener::new(), self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CtrlcSink;
+    use crate::{listener::Listener, KeyboardInterrupt};
+    use futures::{prelude::*, stream, sync::mpsc};
+
+    #[test]
+    fn test_ctrlc_sink() {
+        let (tx, rx) = mpsc::unbounded::<u32>();
+        let tx = tx.sink_map_err(|_| failure::err_msg("receiver gone"));
+        let ctrlc = Listener::interrupted();
+        let sink = CtrlcSink::with_listener(ctrlc, tx);
+        let err = match sink
+            .send_all(stream::iter_ok::<_, failure::Error>(0..3))
+            .wait()
+        {
+            Ok(_) => panic!("the sink wasn't interrupted"),
+            Err(err) => err,
+        };
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+        assert_eq!(rx.collect().wait().unwrap(), Vec::<u32>::new());
+
+        let (tx, rx) = mpsc::unbounded::<u32>();
+        let tx = tx.sink_map_err(|_| failure::err_msg("receiver gone"));
+        let ctrlc = Listener::from_stream(Box::new(stream::empty()));
+        let sink = CtrlcSink::with_listener(ctrlc, tx);
+        let (sink, _) = sink
+            .send_all(stream::iter_ok::<_, failure::Error>(0..3))
+            .wait()
+            .unwrap();
+        drop(sink);
+        assert_eq!(rx.collect().wait().unwrap(), vec![0, 1, 2]);
+    }
+}
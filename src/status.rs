@@ -0,0 +1,82 @@
+//! Introspection of the interrupt handling state.
+
+use crate::listener;
+
+/// A snapshot of the interrupt handling state of the process, see `status`.
+#[derive(Debug, Clone)]
+pub struct Status {
+    ctrlc_intercepted: bool,
+    active_wrappers: usize,
+    interrupt_count: usize,
+    unhandled_interrupts: usize,
+}
+
+impl Status {
+    /// Whether the ctrl+c handler is installed.
+    ///
+    /// Once installed, the handler stays in place for the lifetime of the
+    /// process, so ctrl+c is swallowed even when no wrapper is alive. It's
+    /// registered in the background, so it may not be in place yet right after
+    /// the first wrapper was polled.
+    pub fn ctrlc_intercepted(&self) -> bool {
+        self.ctrlc_intercepted
+    }
+
+    /// The number of wrappers currently alive.
+    pub fn active_wrappers(&self) -> usize {
+        self.active_wrappers
+    }
+
+    /// Whether any wrapper has been interrupted since the process started.
+    pub fn interrupted(&self) -> bool {
+        self.interrupt_count > 0
+    }
+
+    /// The number of times ctrl+c has been pressed.
+    ///
+    /// This is the count of the listener shared by the wrappers, so it keeps
+    /// up even while no wrapper is polled. It's meant for progress displays,
+    /// e.g. to tell after the first interrupt that the current item is being
+    /// finished and another ctrl+c aborts it. The interrupts of custom signal
+    /// sources, and of wrappers given their own `reactor`, are not counted.
+    pub fn interrupt_count(&self) -> usize {
+        self.interrupt_count
+    }
+
+    /// The number of times ctrl+c was pressed while no wrapper was alive, e.g.
+    /// during the teardown after the main future was interrupted.
+    ///
+    /// No wrapper is there to be interrupted, and the handler keeps the process
+    /// running, see `exit_on_unhandled_ctrlc` to exit on them instead. Always 0 on
+    /// platforms other than Unix.
+    pub fn unhandled_interrupts(&self) -> usize {
+        self.unhandled_interrupts
+    }
+}
+
+/// Returns the current interrupt handling state, e.g. for health endpoints.
+pub fn status() -> Status {
+    Status {
+        ctrlc_intercepted: listener::installed(),
+        active_wrappers: listener::active(),
+        interrupt_count: crate::shared::received(),
+        #[cfg(unix)]
+        unhandled_interrupts: crate::disposition::unhandled(),
+        #[cfg(not(unix))]
+        unhandled_interrupts: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::status;
+    use crate::CtrlcAsError;
+
+    #[test]
+    fn test_status() {
+        // Other tests run in parallel, so only check what they cannot change
+        let source = futures::stream::empty();
+        let _wrapper = CtrlcAsError::with_signal_source(futures::future::ok::<(), ()>(()), source);
+        assert!(status().active_wrappers() >= 1);
+    }
+}
@@ -0,0 +1,131 @@
+//! Interrupting `std::future::Future`s, for `async fn` and `.await`.
+//!
+//! The signal handling is still driven by tokio 0.1: outside of a tokio 0.1
+//! runtime, a background reactor is used, so the wrappers work on any
+//! executor, e.g. tokio 1.x or `futures::executor`. The timer-based features,
+//! like `HandlerErrorPolicy::Reregister`, need a tokio 0.1 timer.
+//!
+//! # Examples
+//! ```
+//!     use tokio_ctrlc_error::std_future::AsyncCtrlc;
+//!
+//!     async fn lengthy_task() -> Result<(), failure::Error> {
+//!         Ok(())
+//!     }
+//!
+//!     let task = async { lengthy_task().ctrlc_as_error().await };
+//!     futures03::executor::block_on(task).unwrap();
+//! ```
+
+use crate::{listener::Listener, InterruptOptions, IoError, KeyboardInterrupt};
+use ::futures03::compat::Compat01As03;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// The ctrl+c listener as a futures 0.1 future, to be adapted to futures 0.3.
+struct Interrupted(Listener);
+
+impl futures::Future for Interrupted {
+    type Error = IoError;
+    type Item = ();
+
+    fn poll(&mut self) -> futures::Poll<(), IoError> {
+        self.0.poll()
+    }
+}
+
+/// Future returned by `ctrlc_as_error`.
+pub struct CtrlcAsError<F> {
+    ctrlc: Compat01As03<Interrupted>,
+    future: F,
+}
+
+impl<F> CtrlcAsError<F> {
+    fn new(ctrlc: Listener, future: F) -> Self {
+        CtrlcAsError {
+            ctrlc: Compat01As03::new(Interrupted(ctrlc)),
+            future,
+        }
+    }
+}
+
+impl<F, T, E> Future for CtrlcAsError<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: From<KeyboardInterrupt> + From<IoError>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        // Safety: `future` is pinned along with `self` and never moved out,
+        // `ctrlc` is `Unpin`
+        let this = unsafe { self.get_unchecked_mut() };
+        match Pin::new(&mut this.ctrlc).poll(cx) {
+            Poll::Ready(Ok(())) => {
+                return Poll::Ready(Err(this.ctrlc.get_mut().0.interrupt().into()))
+            }
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+            Poll::Pending => {}
+        }
+        unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx)
+    }
+}
+
+pub trait AsyncCtrlc<F: Future> {
+    /// Intercept ctrl+c during execution and return an error in such case.
+    fn ctrlc_as_error(self) -> CtrlcAsError<F>;
+
+    /// Like `ctrlc_as_error`, but configured with `options`.
+    fn ctrlc_as_error_with<O: Into<InterruptOptions>>(self, options: O) -> CtrlcAsError<F>;
+}
+
+impl<F, T, E> AsyncCtrlc<F> for F
+where
+    F: Future<Output = Result<T, E>>,
+    E: From<KeyboardInterrupt> + From<IoError>,
+{
+    fn ctrlc_as_error(self) -> CtrlcAsError<F> {
+        self.ctrlc_as_error_with(InterruptOptions::default())
+    }
+
+    fn ctrlc_as_error_with<O: Into<InterruptOptions>>(self, options: O) -> CtrlcAsError<F> {
+        CtrlcAsError::new(Listener::with_options(options.into()), self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AsyncCtrlc, CtrlcAsError};
+    use crate::{listener::Listener, KeyboardInterrupt};
+    use std::future;
+
+    async fn answer() -> Result<u32, failure::Error> {
+        Ok(42)
+    }
+
+    #[test]
+    fn test_futures_executor() {
+        let res = futures03::executor::block_on(answer().ctrlc_as_error());
+        assert_eq!(res.unwrap(), 42);
+    }
+
+    #[test]
+    fn test_tokio1() {
+        let rt = tokio1::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let res = rt.block_on(async { answer().ctrlc_as_error().await });
+        assert_eq!(res.unwrap(), 42);
+    }
+
+    #[test]
+    fn test_interrupted() {
+        let ctrlc = Listener::interrupted();
+        let future = CtrlcAsError::new(ctrlc, future::pending::<Result<(), failure::Error>>());
+        let err = futures03::executor::block_on(future).unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+    }
+}
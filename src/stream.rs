@@ -0,0 +1,291 @@
+//! Interrupting streams.
+
+use crate::{listener::Listener, InterruptOptions, IoError, KeyboardInterrupt};
+use futures::{prelude::*, stream::Zip};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Drain {
+    Running,
+    /// Interrupted once, yielding only the items that are ready.
+    Draining,
+    Done,
+}
+
+/// Stream returned by `ctrlc_drain`.
+pub struct CtrlcDrain<S> {
+    ctrlc: Listener,
+    stream: S,
+    drain: Drain,
+}
+
+impl<S: Stream> Stream for CtrlcDrain<S>
+where
+    S::Error: From<KeyboardInterrupt> + From<IoError>,
+{
+    type Error = S::Error;
+    type Item = S::Item;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if self.drain == Drain::Done {
+            return Ok(Async::Ready(None));
+        }
+        while self.ctrlc.poll()?.is_ready() {
+            if self.drain == Drain::Draining {
+                self.drain = Drain::Done;
+                return Err(self.ctrlc.interrupt().into());
+            }
+            self.drain = Drain::Draining;
+        }
+        match self.stream.poll()? {
+            Async::Ready(Some(item)) => Ok(Async::Ready(Some(item))),
+            Async::Ready(None) => {
+                self.drain = Drain::Done;
+                Ok(Async::Ready(None))
+            }
+            // Nothing is buffered anymore
+            Async::NotReady if self.drain == Drain::Draining => {
+                self.drain = Drain::Done;
+                Ok(Async::Ready(None))
+            }
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+/// Stream returned by `ctrlc_as_error`, `merge_all` and `zip`.
+///
+/// Yields the items of the wrapped stream, or a `KeyboardInterrupt` error on
+/// ctrl+c, after which it ends.
+pub struct CtrlcAsErrorStream<S> {
+    ctrlc: Listener,
+    stream: S,
+    done: bool,
+}
+
+impl<S> CtrlcAsErrorStream<S> {
+    fn new(stream: S) -> Self {
+        Self::with_options(stream, InterruptOptions::default())
+    }
+
+    fn with_options(stream: S, options: InterruptOptions) -> Self {
+        CtrlcAsErrorStream {
+            ctrlc: Listener::with_options(options),
+            stream,
+            done: false,
+        }
+    }
+}
+
+impl<S: Stream> Stream for CtrlcAsErrorStream<S>
+where
+    S::Error: From<KeyboardInterrupt> + From<IoError>,
+{
+    type Error = S::Error;
+    type Item = S::Item;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if self.done {
+            return Ok(Async::Ready(None));
+        }
+        if self.ctrlc.poll()?.is_ready() {
+            self.done = true;
+            return Err(self.ctrlc.interrupt().into());
+        }
+        self.stream.poll()
+    }
+}
+
+/// The streams merged by `merge_all`.
+pub struct MergeAll<S> {
+    streams: Vec<S>,
+    /// The stream to poll first, so that a busy stream doesn't starve the
+    /// others.
+    next: usize,
+}
+
+impl<S: Stream> Stream for MergeAll<S> {
+    type Error = S::Error;
+    type Item = S::Item;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let mut polled = 0;
+        while polled < self.streams.len() {
+            let i = (self.next + polled) % self.streams.len();
+            match self.streams[i].poll()? {
+                Async::Ready(Some(item)) => {
+                    self.next = i + 1;
+                    return Ok(Async::Ready(Some(item)));
+                }
+                Async::Ready(None) => {
+                    self.streams.remove(i);
+                }
+                Async::NotReady => polled += 1,
+            }
+        }
+        if self.streams.is_empty() {
+            Ok(Async::Ready(None))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+/// Merge the streams, yielding the items as they arrive, until ctrl+c.
+///
+/// The merged stream ends once all the streams end. On ctrl+c, it yields a
+/// `KeyboardInterrupt` error and ends. All the streams share a single ctrl+c
+/// listener.
+pub fn merge_all<I>(streams: I) -> CtrlcAsErrorStream<MergeAll<I::Item>>
+where
+    I: IntoIterator,
+    I::Item: Stream,
+    <I::Item as Stream>::Error: From<KeyboardInterrupt> + From<IoError>,
+{
+    CtrlcAsErrorStream::new(MergeAll {
+        streams: streams.into_iter().collect(),
+        next: 0,
+    })
+}
+
+/// Like `Stream::zip`, but interrupted by ctrl+c.
+///
+/// On ctrl+c, the stream yields a `KeyboardInterrupt` error and ends, even if
+/// only one of the streams is waiting.
+pub fn zip<A, B>(a: A, b: B) -> CtrlcAsErrorStream<Zip<A, B>>
+where
+    A: Stream,
+    B: Stream<Error = A::Error>,
+    A::Error: From<KeyboardInterrupt> + From<IoError>,
+{
+    CtrlcAsErrorStream::new(a.zip(b))
+}
+
+pub trait AsyncCtrlcStream<S: Stream> {
+    /// Intercept ctrl+c while consuming the stream.
+    ///
+    /// The items are yielded normally until ctrl+c, when the stream yields a
+    /// `KeyboardInterrupt` error and ends.
+    ///
+    /// # Examples
+    /// ```
+    ///     use futures::prelude::*;
+    ///     use tokio_ctrlc_error::AsyncCtrlcStream;
+    ///
+    ///     let events = futures::stream::iter_ok::<_, failure::Error>(0..3);
+    ///     let task = events.ctrlc_as_error().for_each(|event| {
+    ///         println!("event {}", event);
+    ///         Ok(())
+    ///     });
+    ///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+    ///     rt.block_on(task).unwrap();
+    /// ```
+    fn ctrlc_as_error(self) -> CtrlcAsErrorStream<S>;
+
+    /// Like `ctrlc_as_error`, but configured with `options`.
+    fn ctrlc_as_error_with<O: Into<InterruptOptions>>(self, options: O) -> CtrlcAsErrorStream<S>;
+
+    /// Handle ctrl+c the way data pipelines usually do.
+    ///
+    /// The first interrupt switches the stream into drain mode: the items that
+    /// are already available are still yielded, and the stream ends as soon as
+    /// it would have to wait for more. A second interrupt ends the stream with
+    /// a `KeyboardInterrupt` error right away.
+    fn ctrlc_drain(self) -> CtrlcDrain<S>;
+}
+
+impl<S: Stream> AsyncCtrlcStream<S> for S
+where
+    S::Error: From<KeyboardInterrupt> + From<IoError>,
+{
+    fn ctrlc_as_error(self) -> CtrlcAsErrorStream<S> {
+        CtrlcAsErrorStream::new(self)
+    }
+
+    fn ctrlc_as_error_with<O: Into<InterruptOptions>>(self, options: O) -> CtrlcAsErrorStream<S> {
+        CtrlcAsErrorStream::with_options(self, options.into())
+    }
+
+    fn ctrlc_drain(self) -> CtrlcDrain<S> {
+        CtrlcDrain {
+            ctrlc: Listener::new(),
+            stream: self,
+            drain: Drain::Running,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_all, zip, AsyncCtrlcStream, CtrlcAsErrorStream, CtrlcDrain, Drain};
+    use crate::{listener::Listener, KeyboardInterrupt};
+    use futures::{prelude::*, stream};
+
+    fn interrupted<S>(stream: S) -> CtrlcAsErrorStream<S> {
+        CtrlcAsErrorStream {
+            ctrlc: Listener::interrupted(),
+            stream,
+            done: false,
+        }
+    }
+
+    fn drain<S>(stream: S, interrupts: usize) -> CtrlcDrain<S> {
+        let source = stream::iter_ok(vec![(); interrupts]).chain(stream::empty());
+        CtrlcDrain {
+            ctrlc: Listener::from_stream(Box::new(source)),
+            stream,
+            drain: Drain::Running,
+        }
+    }
+
+    #[test]
+    fn test_drain() {
+        let items = stream::iter_ok::<_, failure::Error>(0..3).chain(stream::empty());
+        let stream = drain(items, 1);
+        assert_eq!(stream.collect().wait().unwrap(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_second_interrupt() {
+        let items = stream::iter_ok::<_, failure::Error>(0..3);
+        let mut stream = drain(items, 2).wait();
+        let err = stream.next().unwrap().unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn test_merge_all() {
+        let streams = vec![
+            stream::iter_ok::<_, failure::Error>(vec![1, 2]),
+            stream::iter_ok(vec![]),
+            stream::iter_ok(vec![3]),
+        ];
+        let mut items = merge_all(streams).collect().wait().unwrap();
+        items.sort();
+        assert_eq!(items, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_zip() {
+        let a = stream::iter_ok::<_, failure::Error>(0..3);
+        let b = stream::iter_ok(vec!["a", "b"]);
+        let items = zip(a, b).collect().wait().unwrap();
+        assert_eq!(items, vec![(0, "a"), (1, "b")]);
+    }
+
+    #[test]
+    fn test_ctrlc_as_error() {
+        let items = stream::iter_ok::<_, failure::Error>(0..3).ctrlc_as_error();
+        let mut rt = tokio::runtime::current_thread::Runtime::new().unwrap();
+        assert_eq!(rt.block_on(items.collect()).unwrap(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_interrupted() {
+        let items = stream::iter_ok::<_, failure::Error>(0..3).chain(stream::empty());
+        let mut stream = interrupted(items).wait();
+        let err = stream.next().unwrap().unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+        assert!(stream.next().is_none());
+    }
+}
@@ -0,0 +1,82 @@
+//! Letting ctrl+c through to child processes for a while.
+
+use futures::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of suspensions currently in effect.
+static SUSPENDED: AtomicUsize = AtomicUsize::new(0);
+
+/// Guard returned by `suspend_ctrlc`, resuming the interception when dropped.
+#[must_use = "the interception is resumed when the guard is dropped"]
+pub struct SuspendGuard(());
+
+impl Drop for SuspendGuard {
+    fn drop(&mut self) {
+        SUSPENDED.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Stop intercepting ctrl+c until the guard is dropped.
+///
+/// The interrupts received in the meantime are discarded by all the wrappers
+/// of the process, rather than deferred like with `uninterruptible_for`. This
+/// is useful while a child process runs in the foreground: it receives the
+/// ctrl+c from the terminal too and handles it on its own. The handler stays
+/// installed, so ctrl+c doesn't terminate this process either. Guards may be
+/// nested; the interception resumes once all of them are dropped.
+pub fn suspend_ctrlc() -> SuspendGuard {
+    SUSPENDED.fetch_add(1, Ordering::AcqRel);
+    SuspendGuard(())
+}
+
+/// Whether ctrl+c is currently not intercepted.
+pub(crate) fn suspended() -> bool {
+    SUSPENDED.load(Ordering::Acquire) > 0
+}
+
+/// Future returned by `ctrlc_suspended`.
+pub struct CtrlcSuspended<F> {
+    guard: Option<SuspendGuard>,
+    future: F,
+}
+
+impl<F: Future> Future for CtrlcSuspended<F> {
+    type Error = F::Error;
+    type Item = F::Item;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if self.guard.is_none() {
+            self.guard = Some(suspend_ctrlc());
+        }
+        let res = self.future.poll();
+        if let Ok(Async::NotReady) = res {
+            return res;
+        }
+        self.guard = None;
+        res
+    }
+}
+
+/// Run `future` without intercepting ctrl+c, see `suspend_ctrlc`.
+///
+/// The interception is suspended from the first poll of `future` until it
+/// completes or is dropped. An interrupt arriving just as it completes may
+/// still reach the wrappers.
+///
+/// # Examples
+/// ```
+///     use futures::prelude::*;
+///     use tokio_ctrlc_error::{ctrlc_suspended, AsyncCtrlc};
+///
+///     // e.g. waiting for an interactive child process
+///     let child = futures::future::ok::<_, failure::Error>(());
+///     let task = ctrlc_suspended(child).ctrlc_as_error();
+///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+///     rt.block_on(task).unwrap();
+/// ```
+pub fn ctrlc_suspended<F: IntoFuture>(future: F) -> CtrlcSuspended<F::Future> {
+    CtrlcSuspended {
+        guard: None,
+        future: future.into_future(),
+    }
+}
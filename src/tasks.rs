@@ -0,0 +1,199 @@
+//! The tasks still running during a shutdown, for the dump on a second ctrl+c.
+
+use std::{
+    fmt,
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+struct Task {
+    id: usize,
+    name: &'static str,
+    started: Instant,
+}
+
+/// The tasks registered with `track_task` that haven't finished yet.
+static TASKS: Mutex<Vec<Task>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Guard returned by `track_task`, unregistering the task when dropped.
+#[must_use = "the task is unregistered when the guard is dropped"]
+pub struct TaskGuard {
+    id: usize,
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        TASKS.lock().unwrap().retain(|task| task.id != self.id);
+    }
+}
+
+/// Register the task `name` as running until the guard is dropped.
+///
+/// The tasks still running are listed when a second ctrl+c forces a cleanup
+/// with `dump_tasks`, to tell what blocks the shutdown. The stages of a
+/// `Pipeline` are registered on their own.
+pub fn track_task(name: &'static str) -> TaskGuard {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    TASKS.lock().unwrap().push(Task {
+        id,
+        name,
+        started: Instant::now(),
+    });
+    TaskGuard { id }
+}
+
+/// A task registered with `track_task`, see `running_tasks`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunningTask {
+    name: &'static str,
+    elapsed: Duration,
+}
+
+impl RunningTask {
+    /// The name the task was registered with.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// How long the task has been running.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+/// Returns the tasks registered with `track_task` that are still running,
+/// the oldest first.
+pub fn running_tasks() -> Vec<RunningTask> {
+    TASKS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|task| RunningTask {
+            name: task.name,
+            elapsed: task.started.elapsed(),
+        })
+        .collect()
+}
+
+/// Returns how many tasks registered with `track_task` are still running.
+pub(crate) fn running_count() -> usize {
+    TASKS.lock().unwrap().len()
+}
+
+/// The progress of a cleanup waiting for the tasks registered with
+/// `track_task`, see `CtrlcWithCleanup::on_progress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrainProgress {
+    remaining: usize,
+    total: usize,
+    elapsed: Duration,
+}
+
+impl DrainProgress {
+    pub(crate) fn new(remaining: usize, total: usize, elapsed: Duration) -> Self {
+        DrainProgress {
+            remaining,
+            total: total.max(remaining),
+            elapsed,
+        }
+    }
+
+    /// How many tasks are still running.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// How many tasks were running when the cleanup started, or more if some
+    /// were started since.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// The time spent in the cleanup.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+impl fmt::Display for DrainProgress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} tasks remaining after {}s",
+            self.remaining,
+            self.total,
+            self.elapsed.as_secs()
+        )
+    }
+}
+
+/// Where to report the progress of a cleanup, e.g. a log watched by the
+/// operators of a service, to tell a slow shutdown from a hung one. It is
+/// implemented for closures.
+pub trait ProgressReporter {
+    /// Report `progress`, once per period of the watchdog.
+    fn report(&mut self, progress: &DrainProgress);
+}
+
+impl<R: FnMut(&DrainProgress)> ProgressReporter for R {
+    fn report(&mut self, progress: &DrainProgress) {
+        self(progress)
+    }
+}
+
+/// Print the tasks still running to stderr, after a shutdown was forced
+/// `elapsed` into the cleanup.
+pub(crate) fn dump(elapsed: Duration) {
+    let tasks = running_tasks();
+    let stderr = io::stderr();
+    let mut stderr = stderr.lock();
+    // Nothing more can be done if stderr is gone
+    let _ = writeln!(
+        stderr,
+        "shutdown forced after {:.1}s, tasks still running: {}",
+        elapsed.as_secs_f64(),
+        tasks.len()
+    );
+    for task in tasks {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            target: "tokio_ctrlc_error::shutdown",
+            task = task.name,
+            elapsed = ?task.elapsed,
+            "task still running"
+        );
+        let _ = writeln!(
+            stderr,
+            "  {} (running for {:.1}s)",
+            task.name,
+            task.elapsed.as_secs_f64()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{running_tasks, track_task, DrainProgress};
+    use std::time::Duration;
+
+    #[test]
+    fn test_track_task() {
+        let guard = track_task("tasks::test");
+        let running = |name| running_tasks().iter().any(|task| task.name() == name);
+        assert!(running("tasks::test"));
+        drop(guard);
+        assert!(!running("tasks::test"));
+    }
+
+    #[test]
+    fn test_drain_progress() {
+        let progress = DrainProgress::new(3, 7, Duration::from_secs(12));
+        assert_eq!(progress.to_string(), "3/7 tasks remaining after 12s");
+        assert_eq!(DrainProgress::new(2, 1, Duration::default()).total(), 2);
+    }
+}
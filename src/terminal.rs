@@ -0,0 +1,246 @@
+//! Interruptible interactive input.
+
+use crate::{listener::Listener, EndOfInput, IoError, KeyboardInterrupt};
+use futures::{prelude::*, sync::oneshot, task::AtomicTask};
+use std::{
+    io::{self, BufRead, Write},
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex, OnceLock, Weak,
+    },
+    thread,
+};
+
+pub(crate) type Line = io::Result<Option<String>>;
+
+/// Returns the channel of the thread reading stdin.
+///
+/// There is a single reader thread, so that a line typed after an interrupted
+/// prompt is not lost to a thread nobody listens to: it is kept and returned
+/// by the next read.
+fn reader() -> &'static Mutex<mpsc::Sender<oneshot::Sender<Line>>> {
+    static READER: OnceLock<Mutex<mpsc::Sender<oneshot::Sender<Line>>>> = OnceLock::new();
+    READER.get_or_init(|| {
+        let (tx, rx) = mpsc::channel::<oneshot::Sender<Line>>();
+        thread::spawn(move || {
+            let mut pending = None;
+            for reply in rx {
+                let line = pending.take().unwrap_or_else(read_line);
+                if let Err(line) = reply.send(line) {
+                    pending = Some(line);
+                }
+            }
+        });
+        Mutex::new(tx)
+    })
+}
+
+/// Whether the reader thread has reached the end of stdin.
+static EOF: AtomicBool = AtomicBool::new(false);
+
+/// The tasks of the `EndOfInputAsError` futures waiting for `EOF`.
+fn eof_waiters() -> &'static Mutex<Vec<Weak<AtomicTask>>> {
+    static WAITERS: OnceLock<Mutex<Vec<Weak<AtomicTask>>>> = OnceLock::new();
+    WAITERS.get_or_init(Default::default)
+}
+
+fn read_line() -> Line {
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        EOF.store(true, Ordering::SeqCst);
+        for task in eof_waiters().lock().unwrap().drain(..) {
+            if let Some(task) = task.upgrade() {
+                task.notify();
+            }
+        }
+        return Ok(None);
+    }
+    let len = line.trim_end_matches(&['\r', '\n'][..]).len();
+    line.truncate(len);
+    Ok(Some(line))
+}
+
+/// Request a line from the reader thread.
+pub(crate) fn request_line() -> oneshot::Receiver<Line> {
+    let (tx, rx) = oneshot::channel();
+    // The reader thread never exits, so sending cannot fail
+    let _ = reader().lock().unwrap().send(tx);
+    rx
+}
+
+/// Print `message` and request a line from the reader thread.
+pub(crate) fn ask(message: &str) -> oneshot::Receiver<Line> {
+    let mut stdout = io::stdout();
+    // A prompt that fails to show up is not worth failing the read for
+    let _ = stdout.write_all(message.as_bytes());
+    let _ = stdout.flush();
+    request_line()
+}
+
+/// Future returned by `prompt`.
+pub struct Prompt<E> {
+    ctrlc: Listener,
+    line: oneshot::Receiver<Line>,
+    error: PhantomData<fn() -> E>,
+}
+
+/// Print `message` and read a line from stdin, interrupted by ctrl+c.
+///
+/// Resolves to the line without the trailing newline, or to `None` at the end
+/// of input. On ctrl+c a newline is printed, so that further output doesn't
+/// continue the prompt line, and a `KeyboardInterrupt` error is returned.
+///
+/// # Examples
+/// ```no_run
+///     use tokio_ctrlc_error::prompt;
+///
+///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+///     let name = rt.block_on(prompt::<failure::Error>("Your name: ")).unwrap();
+///     println!("Hello, {}!", name.unwrap_or_default());
+/// ```
+pub fn prompt<E>(message: &str) -> Prompt<E> {
+    Prompt {
+        ctrlc: Listener::new(),
+        line: ask(message),
+        error: PhantomData,
+    }
+}
+
+impl<E> Future for Prompt<E>
+where
+    E: From<KeyboardInterrupt> + From<IoError> + From<io::Error>,
+{
+    type Error = E;
+    type Item = Option<String>;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if self.ctrlc.poll()?.is_ready() {
+            println!();
+            return Err(self.ctrlc.interrupt().into());
+        }
+        match self.line.poll() {
+            Ok(Async::Ready(line)) => Ok(Async::Ready(line?)),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(oneshot::Canceled) => unreachable!("the stdin reader never exits"),
+        }
+    }
+}
+
+/// Stream returned by `stdin_lines`.
+pub struct StdinLines<E> {
+    ctrlc: Listener,
+    /// The line being read, `None` once the stream ended.
+    line: Option<oneshot::Receiver<Line>>,
+    error: PhantomData<fn() -> E>,
+}
+
+/// Read stdin line by line, interrupted by ctrl+c.
+///
+/// Yields the lines without the trailing newlines, and ends at the end of
+/// input. On ctrl+c, a `KeyboardInterrupt` error is yielded and the stream
+/// ends. The lines are read by the same thread as `prompt`, so a line typed
+/// after an interrupt is kept for the next read.
+///
+/// # Examples
+/// ```no_run
+///     use futures::prelude::*;
+///     use tokio_ctrlc_error::stdin_lines;
+///
+///     let count = stdin_lines::<failure::Error>().fold(0, |count, _| Ok::<_, failure::Error>(count + 1));
+///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+///     println!("{} lines", rt.block_on(count).unwrap());
+/// ```
+pub fn stdin_lines<E>() -> StdinLines<E> {
+    StdinLines {
+        ctrlc: Listener::new(),
+        line: Some(request_line()),
+        error: PhantomData,
+    }
+}
+
+impl<E> Stream for StdinLines<E>
+where
+    E: From<KeyboardInterrupt> + From<IoError> + From<io::Error>,
+{
+    type Error = E;
+    type Item = String;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let line = match self.line {
+            Some(ref mut line) => line,
+            None => return Ok(Async::Ready(None)),
+        };
+        if self.ctrlc.poll()?.is_ready() {
+            self.line = None;
+            return Err(self.ctrlc.interrupt().into());
+        }
+        let res = match line.poll() {
+            Ok(Async::Ready(res)) => res,
+            Ok(Async::NotReady) => return Ok(Async::NotReady),
+            Err(oneshot::Canceled) => unreachable!("the stdin reader never exits"),
+        };
+        match res {
+            Ok(Some(line)) => {
+                self.line = Some(request_line());
+                Ok(Async::Ready(Some(line)))
+            }
+            Ok(None) => {
+                self.line = None;
+                Ok(Async::Ready(None))
+            }
+            Err(e) => {
+                self.line = None;
+                Err(e.into())
+            }
+        }
+    }
+}
+
+/// Future returned by `end_of_input_as_error`.
+pub struct EndOfInputAsError<F> {
+    task: Arc<AtomicTask>,
+    future: F,
+}
+
+impl<F: Future> Future for EndOfInputAsError<F>
+where
+    F::Error: From<EndOfInput>,
+{
+    type Error = F::Error;
+    type Item = F::Item;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.task.register();
+        if EOF.load(Ordering::SeqCst) {
+            Err(EndOfInput::new().into())
+        } else {
+            self.future.poll()
+        }
+    }
+}
+
+pub trait AsyncEndOfInput<F: Future> {
+    /// Return an `EndOfInput` error once the end of stdin is reached.
+    ///
+    /// The end of input is observed by the reads done through this crate, e.g.
+    /// by `prompt`, so that no input is taken away from the wrapped future. This
+    /// allows a prompt nested deep inside an interactive session to end the
+    /// whole session when the user presses ctrl+d.
+    ///
+    /// Combine with `ctrlc_as_error` to end the session on ctrl+c as well.
+    fn end_of_input_as_error(self) -> EndOfInputAsError<F>;
+}
+
+impl<F: Future> AsyncEndOfInput<F> for F
+where
+    F::Error: From<EndOfInput>,
+{
+    fn end_of_input_as_error(self) -> EndOfInputAsError<F> {
+        let task = Arc::new(AtomicTask::new());
+        let mut waiters = eof_waiters().lock().unwrap();
+        waiters.retain(|task| task.strong_count() > 0);
+        waiters.push(Arc::downgrade(&task));
+        EndOfInputAsError { task, future: self }
+    }
+}
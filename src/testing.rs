@@ -0,0 +1,116 @@
+//! Interrupts injected by the tests, instead of signals.
+//!
+//! Real interrupts are delivered to the whole process, see the `fixture`
+//! module. The unit tests of an application can instead wrap their futures
+//! with a mock signal source, and trigger the interrupts deterministically.
+
+use crate::CtrlcAsError;
+use futures::{prelude::*, sync::mpsc};
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+/// A source of fake interrupts, shared by all its clones.
+///
+/// # Examples
+/// ```
+///     use futures::prelude::*;
+///     use tokio_ctrlc_error::{testing::MockSignals, KeyboardInterrupt};
+///
+///     let signals = MockSignals::new();
+///     let task = signals.wrap(futures::future::empty::<(), failure::Error>());
+///     signals.trigger();
+///     let err = task.wait().unwrap_err();
+///     assert!(err.downcast_ref::<KeyboardInterrupt>().is_some());
+/// ```
+#[derive(Clone, Default)]
+pub struct MockSignals {
+    sources: Arc<Mutex<Vec<mpsc::UnboundedSender<io::Result<()>>>>>,
+}
+
+impl MockSignals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a stream of the interrupts triggered from now on, for
+    /// `CtrlcAsError::with_signal_source` and the like.
+    ///
+    /// The stream ends once all the clones of `self` are dropped.
+    pub fn source(&self) -> MockSource {
+        let (tx, rx) = mpsc::unbounded();
+        self.sources.lock().unwrap().push(tx);
+        MockSource(rx)
+    }
+
+    /// Wrap `future` like `ctrlc_as_error`, interrupting it on `trigger`
+    /// rather than on ctrl+c.
+    pub fn wrap<F: Future>(&self, future: F) -> CtrlcAsError<F> {
+        CtrlcAsError::with_signal_source(future, self.source())
+    }
+
+    /// Interrupt all the futures wrapped with the sources of `self`.
+    pub fn trigger(&self) {
+        self.send(Ok(()));
+    }
+
+    /// Make the sources fail with `err`, as if the ctrl+c handler failed.
+    pub fn fail(&self, err: io::ErrorKind) {
+        self.send(Err(err.into()));
+    }
+
+    fn send(&self, event: io::Result<()>) {
+        let mut sources = self.sources.lock().unwrap();
+        sources.retain(|tx| {
+            let event = match event {
+                Ok(()) => Ok(()),
+                Err(ref e) => Err(e.kind().into()),
+            };
+            tx.unbounded_send(event).is_ok()
+        });
+    }
+}
+
+/// Stream returned by `MockSignals::source`.
+pub struct MockSource(mpsc::UnboundedReceiver<io::Result<()>>);
+
+impl Stream for MockSource {
+    type Error = io::Error;
+    type Item = ();
+
+    fn poll(&mut self) -> Poll<Option<()>, io::Error> {
+        match self.0.poll() {
+            Ok(Async::Ready(Some(event))) => event.map(|()| Async::Ready(Some(()))),
+            Ok(Async::Ready(None)) => Ok(Async::Ready(None)),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(()) => unreachable!("receivers don't fail"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MockSignals;
+    use crate::IoError;
+    use futures::{future, prelude::*};
+    use std::io;
+
+    #[test]
+    fn test_mock_signals() {
+        let signals = MockSignals::new();
+        let mut first = signals.wrap(future::empty::<(), failure::Error>());
+        let second = signals.clone().wrap(future::empty::<(), failure::Error>());
+        // Not interrupted before the trigger
+        let pending = future::lazy(|| Ok::<_, ()>(first.poll().unwrap().is_not_ready()));
+        assert!(pending.wait().unwrap());
+        signals.trigger();
+        assert!(first.wait().is_err());
+        assert!(second.wait().is_err());
+
+        let task = signals.wrap(future::empty::<(), failure::Error>());
+        signals.fail(io::ErrorKind::PermissionDenied);
+        let err = task.wait().unwrap_err();
+        assert!(err.downcast_ref::<IoError>().is_some());
+    }
+}
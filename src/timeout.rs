@@ -0,0 +1,127 @@
+//! Racing a future against both ctrl+c and a deadline.
+
+use crate::{
+    deadlines::Deadlines, listener::Listener, DeadlineExceeded, IoError, KeyboardInterrupt,
+};
+use futures::prelude::*;
+use std::time::{Duration, Instant};
+
+/// Future returned by `ctrlc_or_timeout`.
+pub struct CtrlcOrTimeout<F> {
+    ctrlc: Listener,
+    /// The deadlines, with the timeout reported when they elapse.
+    deadlines: Deadlines<Duration>,
+    started: Instant,
+    future: F,
+}
+
+impl<F> CtrlcOrTimeout<F> {
+    pub(crate) fn new(future: F, timeout: Duration) -> Self {
+        Self::with_listener(Listener::new(), future, timeout)
+    }
+
+    fn with_listener(ctrlc: Listener, future: F, timeout: Duration) -> Self {
+        let started = Instant::now();
+        let mut deadlines = Deadlines::new();
+        deadlines.insert(started + timeout, timeout);
+        CtrlcOrTimeout {
+            ctrlc,
+            deadlines,
+            started,
+            future,
+        }
+    }
+
+    /// Also fail with `DeadlineExceeded` at `deadline`, e.g. the deadline of
+    /// a whole request on top of the timeout of one of its steps.
+    ///
+    /// The error reports the time from the creation of the wrapper to
+    /// `deadline` as the timeout. All the deadlines share the timer of the
+    /// wrapper.
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        let timeout = deadline.saturating_duration_since(self.started);
+        self.deadlines.insert(deadline, timeout);
+        self
+    }
+}
+
+impl<F: Future> Future for CtrlcOrTimeout<F>
+where
+    F::Error: From<KeyboardInterrupt> + From<DeadlineExceeded> + From<IoError>,
+{
+    type Error = F::Error;
+    type Item = F::Item;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if self.ctrlc.poll()?.is_ready() {
+            return Err(self.ctrlc.interrupt().into());
+        }
+        // A future completing right at the deadline is not timed out
+        if let Async::Ready(item) = self.future.poll()? {
+            return Ok(Async::Ready(item));
+        }
+        if let Async::Ready(timeout) = self.deadlines.poll()? {
+            return Err(DeadlineExceeded::new(timeout).into());
+        }
+        Ok(Async::NotReady)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CtrlcOrTimeout;
+    use crate::{listener::Listener, DeadlineExceeded, InterruptKind, IoError, KeyboardInterrupt};
+    use futures::{future, stream, Future};
+    use std::time::{Duration, Instant};
+    use tokio::runtime::current_thread::Runtime;
+
+    #[test]
+    fn test_ctrlc_or_timeout() {
+        let mut rt = Runtime::new().unwrap();
+        let timeout = Duration::from_millis(10);
+
+        let ctrlc = Listener::from_stream(Box::new(stream::empty()));
+        let task =
+            CtrlcOrTimeout::with_listener(ctrlc, future::ok::<_, failure::Error>(3), timeout);
+        assert_eq!(rt.block_on(task).unwrap(), 3);
+
+        let ctrlc = Listener::from_stream(Box::new(stream::empty()));
+        let task =
+            CtrlcOrTimeout::with_listener(ctrlc, future::empty::<(), failure::Error>(), timeout);
+        let err = rt.block_on(task).unwrap_err();
+        assert_eq!(
+            err.downcast::<DeadlineExceeded>().unwrap().timeout(),
+            timeout
+        );
+
+        let ctrlc = Listener::interrupted();
+        let task =
+            CtrlcOrTimeout::with_listener(ctrlc, future::empty::<(), failure::Error>(), timeout);
+        let err = rt.block_on(task).unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+    }
+
+    #[test]
+    fn test_deadline() {
+        let ctrlc = Listener::from_stream(Box::new(stream::empty()));
+        let future = future::empty::<(), failure::Error>();
+        let task = CtrlcOrTimeout::with_listener(ctrlc, future, Duration::from_secs(3600))
+            .deadline(Instant::now() + Duration::from_millis(10));
+        let err = Runtime::new().unwrap().block_on(task).unwrap_err();
+        let timeout = err.downcast::<DeadlineExceeded>().unwrap().timeout();
+        assert!(timeout >= Duration::from_millis(10), "{:?}", timeout);
+        assert!(timeout < Duration::from_secs(1), "{:?}", timeout);
+    }
+
+    #[test]
+    fn test_without_timer() {
+        let ctrlc = Listener::from_stream(Box::new(stream::empty()));
+        let timeout = Duration::from_millis(10);
+        let task =
+            CtrlcOrTimeout::with_listener(ctrlc, future::empty::<(), failure::Error>(), timeout);
+        let err = task.wait().unwrap_err().downcast::<IoError>().unwrap();
+        assert!(err.is_timer());
+        assert_eq!(err.interrupt_kind(), InterruptKind::Timer);
+        assert!(err.to_string().starts_with("timer error"), "{}", err);
+    }
+}
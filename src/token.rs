@@ -0,0 +1,262 @@
+//! A handle to the interrupt, shared by the components of the program.
+
+use crate::{listener::Listener, KeyboardInterrupt};
+use futures::{prelude::*, task::AtomicTask};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, Weak,
+};
+
+struct Shared {
+    interrupted: AtomicBool,
+    /// The interrupt returned by the wrapped futures, set before `interrupted`.
+    interrupt: Mutex<Option<KeyboardInterrupt>>,
+    /// The tasks of the futures waiting for the interrupt.
+    waiters: Mutex<Vec<Weak<AtomicTask>>>,
+    /// The task listening for ctrl+c, which stops with the last token.
+    watcher: Arc<AtomicTask>,
+}
+
+impl Shared {
+    fn interrupt(&self, interrupt: KeyboardInterrupt) {
+        *self.interrupt.lock().unwrap() = Some(interrupt);
+        self.interrupted.store(true, Ordering::SeqCst);
+        for task in self.waiters.lock().unwrap().drain(..) {
+            if let Some(task) = task.upgrade() {
+                task.notify();
+            }
+        }
+    }
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        self.watcher.notify();
+    }
+}
+
+struct Watcher {
+    ctrlc: Listener,
+    shared: Weak<Shared>,
+    task: Arc<AtomicTask>,
+}
+
+impl Future for Watcher {
+    type Error = ();
+    type Item = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        self.task.register();
+        let shared = match self.shared.upgrade() {
+            Some(shared) => shared,
+            None => return Ok(Async::Ready(())),
+        };
+        match self.ctrlc.poll() {
+            Ok(Async::Ready(())) => shared.interrupt(self.ctrlc.interrupt()),
+            Ok(Async::NotReady) => return Ok(Async::NotReady),
+            // A failing handler stops the watcher, the token won't be interrupted
+            Err(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    target: "tokio_ctrlc_error",
+                    error = %_err,
+                    "ctrl+c handler failed, the token will not be interrupted"
+                );
+            }
+        }
+        Ok(Async::Ready(()))
+    }
+}
+
+/// A cloneable handle to the first ctrl+c, for components that can't be
+/// wrapped with `ctrlc_as_error`.
+///
+/// Unlike the wrappers, which cover only the futures preceding them in the
+/// chain, a token can be handed down to every part of the program. They can
+/// check it, wait for it, or wrap their own futures with it, and all of them
+/// see the same interrupt. Ctrl+c is intercepted as long as any clone of the
+/// token is alive.
+///
+/// # Examples
+/// ```
+///     use futures::prelude::*;
+///     use tokio_ctrlc_error::CtrlcToken;
+///
+///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+///     let token = rt.block_on(futures::future::lazy(|| Ok::<_, ()>(CtrlcToken::install()))).unwrap();
+///
+///     // Deep inside the program
+///     let task = token.wrap(futures::future::ok::<_, failure::Error>(()));
+///     rt.block_on(task).unwrap();
+///     assert!(!token.is_interrupted());
+/// ```
+#[derive(Clone)]
+pub struct CtrlcToken(Arc<Shared>);
+
+impl CtrlcToken {
+    /// Create a token and start listening for ctrl+c on the default executor.
+    ///
+    /// # Panics
+    /// Panics outside of a tokio runtime: the token is updated by a watcher
+    /// task, which is spawned on the runtime right away rather than on the
+    /// first check.
+    pub fn install() -> Self {
+        Self::with_listener(Listener::new())
+    }
+
+    pub(crate) fn with_listener(ctrlc: Listener) -> Self {
+        let task = Arc::new(AtomicTask::new());
+        let shared = Arc::new(Shared {
+            interrupted: AtomicBool::new(false),
+            interrupt: Mutex::new(None),
+            waiters: Mutex::new(Vec::new()),
+            watcher: task.clone(),
+        });
+        tokio_executor::spawn(Watcher {
+            ctrlc,
+            shared: Arc::downgrade(&shared),
+            task,
+        });
+        CtrlcToken(shared)
+    }
+
+    /// Returns whether ctrl+c has been pressed.
+    pub fn is_interrupted(&self) -> bool {
+        self.0.interrupted.load(Ordering::SeqCst)
+    }
+
+    /// Fails with `KeyboardInterrupt` if ctrl+c has been pressed.
+    pub fn check<E: From<KeyboardInterrupt>>(&self) -> Result<(), E> {
+        if self.is_interrupted() {
+            Err(self.interrupt().into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The interrupt seen by the watcher, shared by all the clones.
+    fn interrupt(&self) -> KeyboardInterrupt {
+        self.0.interrupt.lock().unwrap().clone().unwrap_or_default()
+    }
+
+    /// Returns a future resolving once ctrl+c has been pressed.
+    pub fn interrupted(&self) -> Interrupted {
+        Interrupted {
+            token: self.clone(),
+            task: self.waiter(),
+        }
+    }
+
+    /// Wrap `future`, returning a `KeyboardInterrupt` error once ctrl+c has
+    /// been pressed, like `ctrlc_as_error`.
+    pub fn wrap<F>(&self, future: F) -> WithToken<F>
+    where
+        F: Future,
+        F::Error: From<KeyboardInterrupt>,
+    {
+        WithToken {
+            interrupted: self.interrupted(),
+            future,
+        }
+    }
+
+    fn waiter(&self) -> Arc<AtomicTask> {
+        let task = Arc::new(AtomicTask::new());
+        let mut waiters = self.0.waiters.lock().unwrap();
+        waiters.retain(|task| task.strong_count() > 0);
+        waiters.push(Arc::downgrade(&task));
+        task
+    }
+}
+
+/// Future returned by `CtrlcToken::interrupted`. Never fails.
+pub struct Interrupted {
+    token: CtrlcToken,
+    task: Arc<AtomicTask>,
+}
+
+impl Future for Interrupted {
+    type Error = ();
+    type Item = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        self.task.register();
+        if self.token.is_interrupted() {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+/// Future returned by `CtrlcToken::wrap`.
+pub struct WithToken<F> {
+    interrupted: Interrupted,
+    future: F,
+}
+
+impl<F: Future> Future for WithToken<F>
+where
+    F::Error: From<KeyboardInterrupt>,
+{
+    type Error = F::Error;
+    type Item = F::Item;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Ok(Async::Ready(())) = self.interrupted.poll() {
+            Err(self.interrupted.token.interrupt().into())
+        } else {
+            self.future.poll()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CtrlcToken;
+    use crate::{listener::Listener, KeyboardInterrupt};
+    use futures::{future, prelude::*, stream, sync::oneshot};
+    use tokio::runtime::Runtime;
+
+    #[test]
+    fn test_token() {
+        let mut rt = Runtime::new().unwrap();
+        let (tx, rx) = oneshot::channel::<()>();
+        let token = rt
+            .block_on(future::lazy(move || {
+                let source = rx
+                    .into_stream()
+                    .map_err(|_| std::io::Error::other("canceled"));
+                Ok::<_, ()>(CtrlcToken::with_listener(Listener::from_stream(Box::new(
+                    source,
+                ))))
+            }))
+            .unwrap();
+        assert!(token.check::<failure::Error>().is_ok());
+
+        let nested = token.clone();
+        let task = token.wrap(future::empty::<(), failure::Error>());
+        tx.send(()).unwrap();
+        let err = rt.block_on(task).unwrap_err();
+        let err = err.downcast::<KeyboardInterrupt>().unwrap();
+        assert_eq!(err.count(), Some(1));
+        rt.block_on(nested.interrupted()).unwrap();
+        assert!(nested.is_interrupted());
+        let checked = nested.check::<KeyboardInterrupt>().unwrap_err();
+        assert_eq!(checked.first_received(), err.first_received());
+    }
+
+    #[test]
+    fn test_watcher_stops() {
+        let mut rt = Runtime::new().unwrap();
+        let token = rt
+            .block_on(future::lazy(|| {
+                let source = Box::new(stream::empty());
+                Ok::<_, ()>(CtrlcToken::with_listener(Listener::from_stream(source)))
+            }))
+            .unwrap();
+        drop(token);
+        // The runtime is idle only once the watcher has stopped
+        rt.shutdown_on_idle().wait().unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+//! Interrupting futures on conditions other than signals.
+
+use crate::{listener::Listener, IoError, TriggerInterrupt};
+use futures::prelude::*;
+use std::{
+    error::Error,
+    io,
+    time::{Instant, SystemTime},
+};
+use tokio_timer::Delay;
+
+/// Future returned by `interrupt_on`.
+pub struct InterruptOn<F> {
+    trigger: Listener,
+    name: &'static str,
+    future: F,
+}
+
+impl<F: Future> Future for InterruptOn<F>
+where
+    F::Error: From<TriggerInterrupt> + From<IoError>,
+{
+    type Error = F::Error;
+    type Item = F::Item;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if self.trigger.poll()?.is_ready() {
+            Err(TriggerInterrupt::new(self.name).into())
+        } else {
+            self.future.poll()
+        }
+    }
+}
+
+pub trait AsyncTrigger<F: Future> {
+    /// Return a `TriggerInterrupt` error named `name` once `trigger` resolves,
+    /// e.g. at the start of a maintenance window or when a feature flag is
+    /// turned off.
+    ///
+    /// The trigger goes through the same handling as ctrl+c, so e.g. it waits
+    /// for the end of `uninterruptible_for` windows. If the trigger fails, an
+    /// `IoError` is returned. Several triggers, and ctrl+c, can be combined by
+    /// chaining the wrappers; the errors tell them apart by their names.
+    ///
+    /// # Examples
+    /// ```
+    ///     use futures::future;
+    ///     use std::time::{Duration, SystemTime};
+    ///     use tokio_ctrlc_error::{deadline_at, AsyncCtrlc, AsyncTrigger, TriggerInterrupt};
+    ///
+    ///     let stop = SystemTime::now() + Duration::from_millis(10);
+    ///     let job = future::empty::<(), failure::Error>()
+    ///         .interrupt_on("maintenance-window", deadline_at(stop))
+    ///         .ctrlc_as_error();
+    ///     let mut rt = tokio::runtime::Runtime::new().unwrap();
+    ///     let err = rt.block_on(job).unwrap_err();
+    ///     assert_eq!(err.downcast::<TriggerInterrupt>().unwrap().trigger(), "maintenance-window");
+    /// ```
+    fn interrupt_on<T>(self, name: &'static str, trigger: T) -> InterruptOn<F>
+    where
+        T: IntoFuture<Item = ()>,
+        T::Future: Send + 'static,
+        T::Error: Error + Send + Sync + 'static;
+}
+
+impl<F: Future> AsyncTrigger<F> for F
+where
+    F::Error: From<TriggerInterrupt> + From<IoError>,
+{
+    fn interrupt_on<T>(self, name: &'static str, trigger: T) -> InterruptOn<F>
+    where
+        T: IntoFuture<Item = ()>,
+        T::Future: Send + 'static,
+        T::Error: Error + Send + Sync + 'static,
+    {
+        let source = trigger
+            .into_future()
+            .map_err(io::Error::other)
+            .into_stream();
+        InterruptOn {
+            trigger: Listener::for_trigger(name, Box::new(source)),
+            name,
+            future: self,
+        }
+    }
+}
+
+/// A trigger firing at the wall-clock time `time`, or right away if it has
+/// passed.
+///
+/// The time is converted to a deadline when this is called, so later changes
+/// of the system clock are not followed.
+pub fn deadline_at(time: SystemTime) -> Delay {
+    let remaining = time.duration_since(SystemTime::now()).unwrap_or_default();
+    Delay::new(Instant::now() + remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deadline_at, AsyncTrigger};
+    use crate::{InterruptKind, TriggerInterrupt};
+    use futures::{future, sync::oneshot};
+    use std::time::{Duration, Instant, SystemTime};
+    use tokio::runtime::current_thread::Runtime;
+
+    #[test]
+    fn test_interrupt_on() {
+        let (tx, rx) = oneshot::channel::<()>();
+        let job = future::ok::<_, failure::Error>(5).interrupt_on("flag", rx);
+        assert_eq!(Runtime::new().unwrap().block_on(job).unwrap(), 5);
+        drop(tx);
+
+        let job = future::empty::<(), failure::Error>()
+            .interrupt_on("flag", future::ok::<_, oneshot::Canceled>(()))
+            .interrupt_on("deadline", deadline_at(SystemTime::now()));
+        let err = Runtime::new().unwrap().block_on(job).unwrap_err();
+        // The outermost trigger is checked first
+        let err = err.downcast::<TriggerInterrupt>().unwrap();
+        assert_eq!(err.trigger(), "deadline");
+        assert_eq!(InterruptKind::of(&err), Some(InterruptKind::Trigger));
+
+        let past = SystemTime::now() - Duration::from_secs(60);
+        assert!(deadline_at(past).deadline() <= Instant::now());
+    }
+}
@@ -0,0 +1,72 @@
+//! Stress test for many wrappers listening for the same ctrl+c.
+//!
+//! This sends a real ctrl+c to the test process, so it lives in its own test
+//! binary: any other wrapper alive in the same process would be interrupted too.
+//! On Windows, the console event reaches every process attached to the
+//! console, the test runner included, so the test runs again in a child
+//! process with a console of its own.
+#![cfg(any(unix, windows))]
+
+use futures::{future, prelude::*};
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+use tokio_ctrlc_error::{AsyncCtrlc, KeyboardInterrupt};
+use tokio_timer::Delay;
+
+const WRAPPERS: usize = 64;
+
+fn run_wrappers(raise: fn()) {
+    let wrappers: Vec<_> = (0..WRAPPERS)
+        .map(|_| {
+            future::empty::<(), failure::Error>()
+                .ctrlc_as_error()
+                .then(Ok::<_, ()>)
+        })
+        .collect();
+    // The wrappers install their handlers on the first poll, which happens
+    // long before the delay elapses
+    let raise = Delay::new(Instant::now() + Duration::from_millis(100))
+        .map(move |_| raise())
+        .map_err(|_| ());
+
+    let mut rt = Runtime::new().unwrap();
+    let (results, ()) = rt.block_on(future::join_all(wrappers).join(raise)).unwrap();
+    assert_eq!(results.len(), WRAPPERS);
+    for res in results {
+        let err = res.unwrap_err();
+        assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_concurrent_wrappers() {
+    run_wrappers(|| unsafe {
+        libc::raise(libc::SIGINT);
+    });
+}
+
+#[cfg(windows)]
+#[test]
+fn test_concurrent_wrappers() {
+    use std::{env, os::windows::process::CommandExt, process::Command};
+    use windows_sys::Win32::System::{
+        Console::{GenerateConsoleCtrlEvent, CTRL_C_EVENT},
+        Threading::CREATE_NEW_CONSOLE,
+    };
+
+    const CHILD: &str = "TOKIO_CTRLC_CONCURRENT_CHILD";
+    if env::var_os(CHILD).is_some() {
+        // The whole console of the child, which is only attached to it
+        return run_wrappers(|| unsafe {
+            GenerateConsoleCtrlEvent(CTRL_C_EVENT, 0);
+        });
+    }
+    let status = Command::new(env::current_exe().unwrap())
+        .args(["test_concurrent_wrappers", "--exact"])
+        .env(CHILD, "1")
+        .creation_flags(CREATE_NEW_CONSOLE)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
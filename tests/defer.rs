@@ -0,0 +1,33 @@
+//! Interrupts deferred by `uninterruptible_for`.
+//!
+//! The window applies to the whole process, so these tests live in their own
+//! test binary.
+
+use futures::{future, prelude::*, stream};
+use std::time::{Duration, Instant};
+use tokio::runtime::current_thread::Runtime;
+use tokio_ctrlc_error::{uninterruptible_for, CtrlcAsError, KeyboardInterrupt};
+use tokio_timer::Delay;
+
+const WINDOW: Duration = Duration::from_millis(100);
+
+#[test]
+fn test_latched_interrupt() {
+    let start = Instant::now();
+    uninterruptible_for(WINDOW);
+    let source = stream::once(Ok(()));
+    let future = CtrlcAsError::with_signal_source(future::empty::<(), _>(), source);
+    let err: failure::Error = Runtime::new().unwrap().block_on(future).unwrap_err();
+    assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+    assert!(start.elapsed() >= WINDOW);
+}
+
+#[test]
+fn test_completes_within_window() {
+    uninterruptible_for(WINDOW);
+    let source = stream::once(Ok(()));
+    let sleep =
+        Delay::new(Instant::now() + Duration::from_millis(10)).map_err(failure::Error::from);
+    let future = CtrlcAsError::with_signal_source(sleep, source);
+    Runtime::new().unwrap().block_on(future).unwrap();
+}
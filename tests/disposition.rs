@@ -0,0 +1,88 @@
+//! The SIGINT disposition is process-wide, so this lives in its own test binary.
+#![cfg(unix)]
+
+use futures::{task, Async};
+use tokio::runtime::current_thread::Runtime;
+use tokio_ctrlc_error::{
+    diagnose_ctrlc_handler, status, uninstall, AsyncCtrlc, AsyncSignal, HandlerConflict,
+};
+
+fn run_wrapper() {
+    // The handler is registered in the background, wait until it's in place
+    let future = futures::future::poll_fn(|| {
+        if status().ctrlc_intercepted() {
+            return Ok(Async::Ready(()));
+        }
+        task::current().notify();
+        Ok::<_, failure::Error>(Async::NotReady)
+    })
+    .ctrlc_as_error();
+    Runtime::new().unwrap().block_on(future).unwrap();
+}
+
+fn run_signal_wrapper() {
+    let future = futures::future::poll_fn(|| {
+        if signal_handler(libc::SIGUSR1) != libc::SIG_DFL {
+            return Ok(Async::Ready(()));
+        }
+        task::current().notify();
+        Ok::<_, failure::Error>(Async::NotReady)
+    })
+    .signal_as_error(&[libc::SIGUSR1]);
+    Runtime::new().unwrap().block_on(future).unwrap();
+}
+
+fn handler() -> libc::sighandler_t {
+    signal_handler(libc::SIGINT)
+}
+
+fn signal_handler(signum: libc::c_int) -> libc::sighandler_t {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        libc::sigaction(signum, std::ptr::null(), &mut action);
+        action.sa_sigaction
+    }
+}
+
+#[test]
+fn test_disposition() {
+    assert_eq!(diagnose_ctrlc_handler().unwrap(), Ok(()));
+    assert!(!status().ctrlc_intercepted());
+
+    run_wrapper();
+    assert!(status().ctrlc_intercepted());
+    assert_eq!(status().active_wrappers(), 0);
+    assert_eq!(diagnose_ctrlc_handler().unwrap(), Ok(()));
+    let ours = handler();
+    run_signal_wrapper();
+    let ours_usr1 = signal_handler(libc::SIGUSR1);
+
+    // The other signals are restored too
+    uninstall().unwrap();
+    assert_eq!(handler(), libc::SIG_DFL);
+    assert_eq!(signal_handler(libc::SIGUSR1), libc::SIG_DFL);
+    assert!(!status().ctrlc_intercepted());
+    assert_eq!(diagnose_ctrlc_handler().unwrap(), Ok(()));
+
+    run_wrapper();
+    assert_eq!(handler(), ours);
+    assert!(status().ctrlc_intercepted());
+    assert_eq!(signal_handler(libc::SIGUSR1), libc::SIG_DFL);
+    run_signal_wrapper();
+    assert_eq!(signal_handler(libc::SIGUSR1), ours_usr1);
+
+    // Recorded, even though no wrapper is alive
+    assert_eq!(status().unhandled_interrupts(), 0);
+    unsafe {
+        libc::raise(libc::SIGINT);
+    }
+    assert_eq!(status().unhandled_interrupts(), 1);
+
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_IGN);
+    }
+    assert_eq!(
+        diagnose_ctrlc_handler().unwrap(),
+        Err(HandlerConflict::Replaced)
+    );
+}
@@ -0,0 +1,21 @@
+use crate::{fixture, INTERRUPTED};
+
+#[test]
+fn test_hung_cleanup() {
+    let output = fixture(Some("hung-cleanup"));
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        assert_eq!(output.status.signal(), Some(libc::SIGABRT));
+    }
+    // STATUS_STACK_BUFFER_OVERRUN, the code of `abort`
+    #[cfg(windows)]
+    assert_eq!(output.status.code(), Some(0xC000_0409u32 as i32));
+}
+
+#[test]
+fn test_quick_cleanup() {
+    let output = fixture(Some("quick-cleanup"));
+    assert_eq!(output.status.code(), Some(INTERRUPTED));
+}
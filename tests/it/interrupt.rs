@@ -0,0 +1,34 @@
+use crate::{fixture, INTERRUPTED};
+
+#[test]
+fn test_interrupted_child() {
+    let output = fixture(None);
+    assert_eq!(output.status.code(), Some(INTERRUPTED));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, "Interrupted by user\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_signaled_child() {
+    use std::os::unix::process::ExitStatusExt;
+
+    let output = fixture(Some("signaled"));
+    assert_eq!(output.status.signal(), Some(libc::SIGINT));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, "Interrupted by user\n");
+}
+
+#[test]
+fn test_completed_child() {
+    let output = fixture(Some("complete"));
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_interrupted_teardown() {
+    let output = fixture(Some("teardown"));
+    assert_eq!(output.status.code(), Some(INTERRUPTED));
+}
@@ -0,0 +1,22 @@
+//! End-to-end tests with a child process interrupting itself.
+
+use std::process::Output;
+
+mod cleanup;
+mod interrupt;
+
+const FIXTURE: &str = env!("CARGO_BIN_EXE_tokio-ctrlc-fixture");
+
+/// The exit code of the fixture when interrupted.
+#[cfg(unix)]
+const INTERRUPTED: i32 = 130;
+// STATUS_CONTROL_C_EXIT
+#[cfg(windows)]
+const INTERRUPTED: i32 = 0xC000_013Au32 as i32;
+
+/// Run the fixture with `arg`, in a console of its own on Windows.
+fn fixture(arg: Option<&str>) -> Output {
+    let mut command = tokio_ctrlc_error::fixture::command(FIXTURE);
+    command.args(arg);
+    command.output().unwrap()
+}
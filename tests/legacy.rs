@@ -0,0 +1,33 @@
+//! A futures 0.1 wrapper interrupted while awaited on a futures 0.3 executor.
+//!
+//! This sends a real SIGINT to the test process, so it lives in its own test
+//! binary: any other wrapper alive in the same process would be interrupted too.
+#![cfg(all(unix, feature = "legacy-0_1"))]
+
+use futures::future;
+use futures03::{executor, future::poll_fn, Future};
+use std::{pin::Pin, thread, time::Duration};
+use tokio_ctrlc_error::{
+    legacy::{into_std, AsyncCtrlc},
+    KeyboardInterrupt,
+};
+
+#[test]
+fn test_into_std_interrupted() {
+    let mut wrapper = into_std(future::empty::<(), failure::Error>().ctrlc_as_error());
+    let mut raised = false;
+    // Interrupt the process once the wrapper has registered
+    let task = poll_fn(move |cx| {
+        let res = Pin::new(&mut wrapper).poll(cx);
+        if !raised {
+            raised = true;
+            thread::spawn(|| {
+                thread::sleep(Duration::from_millis(100));
+                unsafe { libc::raise(libc::SIGINT) };
+            });
+        }
+        res
+    });
+    let err = executor::block_on(task).unwrap_err();
+    assert!(err.downcast::<KeyboardInterrupt>().is_ok());
+}
@@ -0,0 +1,55 @@
+//! Telling the signals sent by the process to itself from external ones.
+//!
+//! This sends real SIGINTs to the test process, so it lives in its own test
+//! binary: any other wrapper alive in the same process would be interrupted too.
+#![cfg(unix)]
+
+use futures::{future, prelude::*, sync::oneshot};
+use std::{
+    process::Command,
+    thread,
+    time::{Duration, Instant},
+};
+use tokio::runtime::Runtime;
+use tokio_ctrlc_error::{AsyncCtrlc, InterruptOptions, KeyboardInterrupt};
+use tokio_timer::Delay;
+
+const SETTLE: Duration = Duration::from_millis(100);
+
+#[test]
+fn test_ignore_own_signals() {
+    let mut rt = Runtime::new().unwrap();
+    let (tx, mut rx) = oneshot::channel();
+    let options = InterruptOptions::new().ignore_own_signals(true);
+    let filtered = future::empty::<(), failure::Error>()
+        .ctrlc_as_error_with(options)
+        .then(move |res| tx.send(res).map_err(|_| ()));
+    rt.spawn(filtered);
+    thread::sleep(SETTLE);
+
+    // Sent by the process itself
+    let plain = future::empty::<(), failure::Error>()
+        .ctrlc_as_error()
+        .then(Ok::<_, ()>);
+    let raise = Delay::new(Instant::now() + SETTLE)
+        .map(|_| unsafe {
+            libc::raise(libc::SIGINT);
+        })
+        .map_err(|_| ());
+    let (plain, ()) = rt.block_on(plain.join(raise)).unwrap();
+    assert!(plain.unwrap_err().downcast::<KeyboardInterrupt>().is_ok());
+    thread::sleep(SETTLE);
+    assert!(rx.try_recv().unwrap().is_none());
+
+    // Sent by another process
+    let status = Command::new("kill")
+        .args(["-INT", &std::process::id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let filtered = rx.wait().unwrap();
+    assert!(filtered
+        .unwrap_err()
+        .downcast::<KeyboardInterrupt>()
+        .is_ok());
+}
@@ -0,0 +1,52 @@
+//! A ctrl+c seen by wrappers on several runtimes at once.
+//!
+//! This sends a real SIGINT to the test process, so it lives in its own test
+//! binary: any other wrapper alive in the same process would be interrupted too.
+#![cfg(unix)]
+
+use futures::{future, prelude::*, sync::oneshot};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+use tokio::runtime::{current_thread, Runtime};
+use tokio_ctrlc_error::{AsyncCtrlc, KeyboardInterrupt};
+use tokio_timer::Delay;
+
+fn interrupted(res: Result<(), failure::Error>) -> bool {
+    res.unwrap_err().downcast::<KeyboardInterrupt>().is_ok()
+}
+
+#[test]
+fn test_multiple_runtimes() {
+    let (polled_tx, polled_rx) = oneshot::channel();
+    let bridge = thread::spawn(move || {
+        let mut rt = current_thread::Runtime::new().unwrap();
+        let mut polled_tx = Some(polled_tx);
+        let mut wrapper = future::empty::<(), failure::Error>().ctrlc_as_error();
+        // Signal the main thread once the wrapper has registered
+        let task = future::poll_fn(move || {
+            let res = wrapper.poll();
+            if let Some(tx) = polled_tx.take() {
+                let _ = tx.send(());
+            }
+            res
+        });
+        interrupted(rt.block_on(task))
+    });
+
+    let mut rt = Runtime::new().unwrap();
+    let main = future::empty::<(), failure::Error>()
+        .ctrlc_as_error()
+        .then(Ok::<_, ()>);
+    let raise = polled_rx
+        .map_err(|_| ())
+        .and_then(|()| Delay::new(Instant::now() + Duration::from_millis(100)).map_err(|_| ()))
+        .map(|()| unsafe {
+            libc::raise(libc::SIGINT);
+        });
+    let (main, ()) = rt.block_on(main.join(raise)).unwrap();
+
+    assert!(interrupted(main));
+    assert!(bridge.join().unwrap());
+}
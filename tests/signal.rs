@@ -0,0 +1,28 @@
+//! Interrupting futures with other Unix signals.
+//!
+//! This sends a real SIGTERM to the test process, so it lives in its own test
+//! binary.
+#![cfg(unix)]
+
+use futures::{future, prelude::*};
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+use tokio_ctrlc_error::{AsyncSignal, SignalInterrupt};
+use tokio_timer::Delay;
+
+#[test]
+fn test_signal_as_error() {
+    let wrapped = future::empty::<(), failure::Error>()
+        .signal_as_error(&[libc::SIGHUP, libc::SIGTERM])
+        .then(Ok::<_, ()>);
+    let raise = Delay::new(Instant::now() + Duration::from_millis(100))
+        .map(|_| unsafe {
+            libc::raise(libc::SIGTERM);
+        })
+        .map_err(|_| ());
+
+    let mut rt = Runtime::new().unwrap();
+    let (res, ()) = rt.block_on(wrapped.join(raise)).unwrap();
+    let err = res.unwrap_err().downcast::<SignalInterrupt>().unwrap();
+    assert_eq!(err.signal(), libc::SIGTERM);
+}